@@ -137,8 +137,7 @@ fn main() {
     }
 
     loop {
-        world.run_update();
-        world.update_coroutines(1.0);
+        world.tick_real_time();
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
 }
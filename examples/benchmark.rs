@@ -23,16 +23,16 @@ fn add_pawns(entity_manager: &mut EntityManager) {
     println!("All pawns initialized");
 }
 
-fn move_pawns(q: Query<(&mut Position,)>) {
+fn move_pawns(mut q: Query<(&mut Position,)>) {
     println!("Moving pawns");
-    for position in q.fetch() {
+    for mut position in q.iter_mut() {
         position.x += 1.0;
         position.y += 1.0;
     }
 }
 
 fn print_pawns(q: Query<(&Position, &Name)>) {
-    for (position, name) in q.fetch() {
+    for (position, name) in q.iter() {
         println!("Name: {}", name.0);
         println!("Position: x: {}, y: {}", position.x, position.y);
     }
@@ -1,18 +1,21 @@
-//! Demonstrates the runtime conflict detection that stops a system from getting two aliasing
-//! references to the same data.
+//! Demonstrates the conflict detection that stops a system from getting two aliasing references
+//! to the same data.
 //!
-//! Each `SystemParam` a system takes (`&mut EntityManager`, `Query<...>`, `Resource<T>`, ...)
+//! Each `SystemParam` a system takes (`&mut EntityManager`, `Query<...>`, `ResMut<T>`, ...)
 //! is fetched via an unsafe raw-pointer dereference, so nothing at the type level stops a
-//! system from asking for the same underlying data twice. `Coordinator`'s `AccessTracker`
-//! catches that at runtime instead: as soon as two parameters in the same system call would
-//! conflict (same manager/component/resource, at least one of them mutable), it panics with a
-//! clear message *before* the second aliasing reference is ever created.
+//! system from asking for the same underlying data twice. Instead, every parameter declares its
+//! accesses in a `SystemAccess` when the system is registered: if two of them conflict (same
+//! manager/component/resource, at least one of them mutable), `World::add_system` panics with a
+//! clear message — and `World::try_add_system` returns the error — before the system ever runs.
 //!
 //! Run with `cargo run --example system_param_conflicts`.
 
 #![allow(dead_code)]
 use dark_iron_ecs::core::{
-    entity_manager::EntityManager, query::Query, resources::Resource, system::SystemSchedule,
+    entity_manager::EntityManager,
+    query::Query,
+    resources::{Res, ResMut},
+    system::SystemSchedule,
     world::World,
 };
 
@@ -51,8 +54,8 @@ fn main() {
     });
 
     demo("same system takes Query<&mut Health> twice", true, || {
-        fn conflicting(q1: Query<(&mut Health,)>, q2: Query<(&mut Health,)>) {
-            let _ = (q1.fetch(), q2.fetch());
+        fn conflicting(mut q1: Query<(&mut Health,)>, mut q2: Query<(&mut Health,)>) {
+            let _ = (q1.fetch_mut(), q2.fetch_mut());
         }
         let mut world = World::default();
         world.create_entity((Health(1),));
@@ -61,8 +64,8 @@ fn main() {
             .run_startup();
     });
 
-    demo("same system takes Resource<Camera> twice", true, || {
-        fn conflicting(_a: Resource<Camera>, _b: Resource<Camera>) {}
+    demo("same system takes ResMut<Camera> and Res<Camera>", true, || {
+        fn conflicting(_a: ResMut<Camera>, _b: Res<Camera>) {}
         let mut world = World::default();
         world.add_resource(Camera(1.0));
         world
@@ -86,24 +89,28 @@ fn main() {
         },
     );
 
-    // --- these are all legitimate and must NOT panic ---
-
-    demo("Query over different components: fine", false, || {
-        fn ok(q1: Query<(&Health,)>, q2: Query<(&Name,)>) {
-            let _ = (q1.fetch(), q2.fetch());
-        }
-        let mut world = World::default();
-        world.create_entity((Health(1), Name("a".into())));
-        world.add_system(SystemSchedule::Startup, ok).run_startup();
-    });
+    demo(
+        "same system takes &EntityManager and a Query<&mut Health>",
+        true,
+        || {
+            // The shared EntityManager can read every component, including the ones the Query
+            // hands out mutably.
+            fn conflicting(_entities: &EntityManager, mut q: Query<(&mut Health,)>) {
+                let _ = q.fetch_mut();
+            }
+            let mut world = World::default();
+            world.create_entity((Health(1),));
+            world
+                .add_system(SystemSchedule::Startup, conflicting)
+                .run_startup();
+        },
+    );
 
     demo(
-        "&EntityManager (shared) alongside Query<&mut Health>: fine",
+        "&EntityManager (shared) alongside Query<&Health>: fine",
         false,
         || {
-            // Reading the entity list while mutating a component's value doesn't restructure
-            // anything, so this is safe.
-            fn ok(_entities: &EntityManager, q: Query<(&mut Health,)>) {
+            fn ok(_entities: &EntityManager, q: Query<(&Health,)>) {
                 let _ = q.fetch();
             }
             let mut world = World::default();
@@ -112,6 +119,39 @@ fn main() {
         },
     );
 
+    demo(
+        "try_add_system reports the conflict instead of panicking",
+        false,
+        || {
+            fn conflicting(_a: &mut EntityManager, _b: &mut EntityManager) {}
+            let mut world = World::default();
+            match world.try_add_system(SystemSchedule::Startup, conflicting) {
+                Ok(_) => println!("  registered?!"),
+                Err(error) => println!("  rejected: {error}"),
+            }
+        },
+    );
+
+    // --- these are all legitimate and must NOT panic ---
+
+    demo("Query over different components: fine", false, || {
+        fn ok(q1: Query<(&Health,)>, q2: Query<(&Name,)>) {
+            let _ = (q1.fetch(), q2.fetch());
+        }
+        let mut world = World::default();
+        world.create_entity((Health(1), Name("a".into())));
+        world.add_system(SystemSchedule::Startup, ok).run_startup();
+    });
+
+    demo("same system takes Res<Camera> twice: fine", false, || {
+        fn ok(a: Res<Camera>, b: Res<Camera>) {
+            let _ = a.0 + b.0;
+        }
+        let mut world = World::default();
+        world.add_resource(Camera(1.0));
+        world.add_system(SystemSchedule::Startup, ok).run_startup();
+    });
+
     demo(
         "two separate systems each taking &mut EntityManager, run sequentially: fine",
         false,
@@ -1,11 +1,13 @@
 use std::{any::TypeId, collections::HashMap};
 
+use super::resources::ResourceManager;
+
 /// Identifies *what* is being accessed by a [`SystemParam`](super::system::SystemParam), so
-/// conflicting accesses within a single system call can be detected before they produce two
-/// live aliasing references to the same data.
+/// conflicting accesses can be detected before they produce two live aliasing references to the
+/// same data.
 ///
 /// [`Query`](super::query::Query) component accesses aren't tracked through this key — see
-/// [`AccessTracker::track_query`], which can additionally prove two queries can never match the
+/// [`SystemAccess::add_query`], which can additionally prove two queries can never match the
 /// same entity (e.g. via `Without`) and skip the conflict entirely.
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub(crate) enum AccessKey {
@@ -16,102 +18,409 @@ pub(crate) enum AccessKey {
 }
 
 /// One [`Query`](super::query::Query)'s accesses, as registered with
-/// [`AccessTracker::track_query`].
+/// [`SystemAccess::add_query`].
+#[derive(Clone)]
 struct QueryAccess {
     /// Component types actually dereferenced by the query, paired with whether the access is
     /// mutable. Overlap here (with incompatible mutability) between two queries is a real
     /// aliasing risk.
     fetched: Vec<(TypeId, bool)>,
-    /// Component types required to be present via a `With<T>` filter, but never dereferenced.
-    /// Only used to help prove disjointness — never itself a source of conflict.
+    /// Fetched component types that are optional (`Option<&T>`/`Option<&mut T>`): accessed like
+    /// any other fetched type, but not required, so they can't help prove disjointness.
+    optional: Vec<TypeId>,
+    /// Component types required to be present via a constraint (`With<T>`, `Added<T>`,
+    /// `Changed<T>`), but never dereferenced. Within a system they only help prove
+    /// disjointness; across systems they count as reads, since change filters read the
+    /// component's ticks.
     present_only: Vec<TypeId>,
     /// Component types required to be *absent* via a `Without<T>` filter.
     excluded: Vec<TypeId>,
+    /// The query's parameters, for conflict messages.
+    type_name: &'static str,
 }
 
-/// Tracks the accesses made by the [`SystemParam`]s of a single, currently-running system, and
-/// panics as soon as two of them would conflict (any pairing where at least one side is
-/// mutable). Cleared before each system call by the generated `System::run`.
-#[derive(Default)]
-pub(crate) struct AccessTracker {
+impl QueryAccess {
+    /// Component types an entity must have to be matched by this query.
+    fn required(&self) -> impl Iterator<Item = TypeId> + '_ {
+        self.fetched
+            .iter()
+            .map(|(t, _)| *t)
+            .filter(|t| !self.optional.contains(t))
+            .chain(self.present_only.iter().copied())
+    }
+
+    /// Returns whether the query fetches any component mutably.
+    fn writes(&self) -> bool {
+        self.fetched.iter().any(|(_, mutable)| *mutable)
+    }
+
+    /// Returns whether the two queries can be proven to never match the same entity: one
+    /// excludes, via `Without`, a type the other requires.
+    fn is_disjoint_from(&self, other: &QueryAccess) -> bool {
+        other.required().any(|t| self.excluded.contains(&t))
+            || self.required().any(|t| other.excluded.contains(&t))
+    }
+
+    /// Component types this query touches, paired with whether the access is mutable.
+    /// `present_only` types are included as reads when `with_filters` is set.
+    fn touched(&self, with_filters: bool) -> impl Iterator<Item = (TypeId, bool)> + '_ {
+        let filters = if with_filters {
+            self.present_only.as_slice()
+        } else {
+            &[]
+        };
+        self.fetched
+            .iter()
+            .copied()
+            .chain(filters.iter().map(|t| (*t, false)))
+    }
+
+    /// Returns whether the two queries could hand out aliasing references to the same
+    /// component (at least one of them mutable).
+    fn conflicts_with(&self, other: &QueryAccess, with_filters: bool) -> bool {
+        if self.is_disjoint_from(other) {
+            return false;
+        }
+        self.touched(with_filters).any(|(type_id, mutable)| {
+            other
+                .touched(with_filters)
+                .any(|(other_type, other_mutable)| {
+                    other_type == type_id && (mutable || other_mutable)
+                })
+        })
+    }
+}
+
+/// Everything the [`SystemParam`](super::system::SystemParam)s of one system access, declared
+/// once when the system is registered.
+///
+/// [`SystemManager`](super::system::SystemManager) uses it two ways: to reject, at registration,
+/// a system whose own parameters conflict (any pairing where at least one side is mutable), and
+/// to run systems whose accesses don't conflict with each other in parallel.
+#[derive(Default, Clone)]
+pub struct SystemAccess {
     accesses: HashMap<AccessKey, bool>,
     queries: Vec<QueryAccess>,
+    /// Whether the system holds a shared `EntityManager`, through which it can read every
+    /// component of every entity.
+    reads_every_component: bool,
+    /// Whether the system records [`Commands`](super::command::Commands), which may touch any
+    /// entity or resource once applied.
+    applies_commands: bool,
+    /// Whether the system must run alone, because it can reach arbitrary world data (e.g. by
+    /// publishing events whose handlers get the whole `World`).
+    exclusive: bool,
+    /// The type taken by the system's [`In`](super::system::In) parameter, if any, along with
+    /// its name.
+    input: Option<(TypeId, &'static str)>,
+    /// The first conflict found between the system's own parameters, if any.
+    conflict: Option<String>,
 }
 
-impl AccessTracker {
-    /// Registers an access to `key`, panicking if it conflicts with one already registered
-    /// during this system call.
-    pub(crate) fn track(&mut self, key: AccessKey, mutable: bool, type_name: &'static str) {
+impl SystemAccess {
+    /// Registers an access to `key`, recording a conflict if it clashes with one already
+    /// registered for this system.
+    pub(crate) fn add(&mut self, key: AccessKey, mutable: bool, type_name: &'static str) {
         if let Some(existing_mutable) = self.accesses.get(&key) {
             if *existing_mutable || mutable {
-                panic!(
+                self.record_conflict(format!(
                     "SystemParam conflict: '{type_name}' is requested more than once by the \
                      same system with incompatible access (at least one of them is mutable). \
                      Combine the parameters or drop the duplicate."
-                );
+                ));
             }
             return;
         }
+        if self.overlaps_resources(key, mutable) {
+            self.record_conflict(format!(
+                "SystemParam conflict: '{type_name}' and ResourceManager, which reaches every \
+                 resource, are requested by the same system, at least one of them mutably. \
+                 Fetch the resource through the ResourceManager, or drop one of them."
+            ));
+        }
         self.accesses.insert(key, mutable);
     }
 
-    /// Registers a [`Query`](super::query::Query)'s accesses, panicking if they conflict with
-    /// an already-registered query in this system call — unless the two queries can be proven
-    /// to never match the same entity (one excludes, via `Without`, a type the other requires,
-    /// either fetched or via `With`), in which case there's no real aliasing risk and no
-    /// conflict is raised.
-    pub(crate) fn track_query(
+    /// Returns whether adding `key` would overlap an access already registered: a single
+    /// resource and the `ResourceManager` reaching every resource, with at least one of them
+    /// mutable.
+    fn overlaps_resources(&self, key: AccessKey, mutable: bool) -> bool {
+        match key {
+            AccessKey::Resource(_) => self
+                .resource_manager_access()
+                .is_some_and(|manager_mutable| manager_mutable || mutable),
+            AccessKey::Manager(type_id) if type_id == TypeId::of::<ResourceManager>() => {
+                self.accesses.iter().any(|(key, resource_mutable)| {
+                    matches!(key, AccessKey::Resource(_)) && (mutable || *resource_mutable)
+                })
+            }
+            AccessKey::Manager(_) => false,
+        }
+    }
+
+    /// Registers a [`Query`](super::query::Query)'s accesses, recording a conflict if they
+    /// clash with an already-registered query of this system — unless the two queries can be
+    /// proven to never match the same entity (one excludes, via `Without`, a type the other
+    /// requires, either fetched non-optionally or via `With`), in which case there's no real
+    /// aliasing risk.
+    pub(crate) fn add_query(
         &mut self,
         fetched: Vec<(TypeId, bool)>,
+        optional: Vec<TypeId>,
         present_only: Vec<TypeId>,
         excluded: Vec<TypeId>,
         type_name: &'static str,
     ) {
-        let required: Vec<TypeId> = fetched
+        let query = QueryAccess {
+            fetched,
+            optional,
+            present_only,
+            excluded,
+            type_name,
+        };
+        if self.reads_every_component && query.writes() {
+            self.record_conflict(Self::component_reader_conflict(type_name));
+        } else if self
+            .queries
             .iter()
-            .map(|(t, _)| *t)
-            .chain(present_only.iter().copied())
-            .collect();
+            .any(|other| query.conflicts_with(other, false))
+        {
+            self.record_conflict(format!(
+                "SystemParam conflict: Query<{type_name}> conflicts with another Query in the \
+                 same system over a shared component. Add a `Without<...>` filter proving the \
+                 two queries never match the same entity, or split into separate systems."
+            ));
+        }
+        self.queries.push(query);
+    }
 
-        for other in &self.queries {
-            let other_required: Vec<TypeId> = other
-                .fetched
-                .iter()
-                .map(|(t, _)| *t)
-                .chain(other.present_only.iter().copied())
-                .collect();
-
-            let provably_disjoint = other_required.iter().any(|t| excluded.contains(t))
-                || required.iter().any(|t| other.excluded.contains(t));
-            if provably_disjoint {
-                continue;
-            }
+    /// Marks the system as reading every component, through a shared `EntityManager`.
+    pub(crate) fn add_component_reader(&mut self) {
+        if let Some(query) = self.queries.iter().find(|query| query.writes()) {
+            self.record_conflict(Self::component_reader_conflict(query.type_name));
+        }
+        self.reads_every_component = true;
+    }
 
-            for (type_id, mutable) in &fetched {
-                if let Some((_, other_mutable)) =
-                    other.fetched.iter().find(|(t, _)| t == type_id)
-                    && (*mutable || *other_mutable)
-                {
-                    panic!(
-                        "SystemParam conflict: Query<{type_name}> conflicts with another \
-                         Query in the same system over a shared component. Add a \
-                         `Without<...>` filter proving the two queries never match the same \
-                         entity, or split into separate systems."
-                    );
-                }
-            }
+    /// Marks the system as recording [`Commands`](super::command::Commands).
+    pub(crate) fn add_commands(&mut self) {
+        self.applies_commands = true;
+    }
+
+    /// Marks the system as needing the whole world to itself.
+    pub(crate) fn add_exclusive(&mut self) {
+        self.exclusive = true;
+    }
+
+    /// Registers an [`In<T>`](super::system::In) parameter taking a `T`.
+    pub(crate) fn add_input(&mut self, type_id: TypeId, type_name: &'static str) {
+        if self.input.is_some() {
+            self.record_conflict(format!(
+                "SystemParam conflict: a system takes at most one In<...> parameter, but \
+                 In<{type_name}> is its second."
+            ));
         }
+        self.input = Some((type_id, type_name));
+    }
 
-        self.queries.push(QueryAccess {
-            fetched,
-            present_only,
-            excluded,
-        });
+    /// Adds everything `other` accesses, for a system made of two systems running one after
+    /// the other (see [`System::pipe`](super::system::System::pipe)): accesses that would
+    /// conflict within a single system don't conflict across the two. Keeps this system's
+    /// input, since `other`'s is provided by this system.
+    pub(crate) fn extend(&mut self, other: SystemAccess) {
+        for (key, mutable) in other.accesses {
+            *self.accesses.entry(key).or_insert(mutable) |= mutable;
+        }
+        self.queries.extend(other.queries);
+        self.reads_every_component |= other.reads_every_component;
+        self.applies_commands |= other.applies_commands;
+        self.exclusive |= other.exclusive;
+        if let Some(conflict) = other.conflict {
+            self.record_conflict(conflict);
+        }
+    }
+
+    /// Returns the type taken by the system's [`In`](super::system::In) parameter, if any.
+    pub fn input(&self) -> Option<(TypeId, &'static str)> {
+        self.input
+    }
+
+    /// Returns the first conflict between this system's own parameters, if any.
+    pub fn conflict(&self) -> Option<&str> {
+        self.conflict.as_deref()
+    }
+
+    /// Returns whether the system only reads: it has no mutable parameter, doesn't record
+    /// commands and doesn't need the world to itself.
+    pub fn is_read_only(&self) -> bool {
+        !self.exclusive
+            && !self.applies_commands
+            && !self.accesses.values().any(|mutable| *mutable)
+            && !self.queries.iter().any(QueryAccess::writes)
+    }
+
+    /// Returns whether this system and `other` must not run at the same time: one of them is
+    /// exclusive, one writes data the other touches, or one records commands whose effects the
+    /// other could observe.
+    pub fn conflicts_with(&self, other: &SystemAccess) -> bool {
+        if self.exclusive || other.exclusive {
+            return true;
+        }
+        if (self.applies_commands && other.touches_data())
+            || (other.applies_commands && self.touches_data())
+        {
+            return true;
+        }
+        if self.accesses.iter().any(|(key, mutable)| {
+            other
+                .accesses
+                .get(key)
+                .is_some_and(|other_mutable| *mutable || *other_mutable)
+        }) {
+            return true;
+        }
+        if self.overlaps_resources_of(other) || other.overlaps_resources_of(self) {
+            return true;
+        }
+        if (self.reads_every_component && other.queries.iter().any(QueryAccess::writes))
+            || (other.reads_every_component && self.queries.iter().any(QueryAccess::writes))
+        {
+            return true;
+        }
+        self.queries.iter().any(|query| {
+            other
+                .queries
+                .iter()
+                .any(|other_query| query.conflicts_with(other_query, true))
+        })
+    }
+
+    fn component_reader_conflict(type_name: &str) -> String {
+        format!(
+            "SystemParam conflict: Query<{type_name}> writes components the shared \
+             EntityManager of the same system can read. Fetch the components through the \
+             Query only, or split into separate systems."
+        )
+    }
+
+    fn record_conflict(&mut self, conflict: String) {
+        self.conflict.get_or_insert(conflict);
+    }
+
+    fn touches_data(&self) -> bool {
+        !self.accesses.is_empty() || !self.queries.is_empty()
+    }
+
+    /// Returns whether this system's `ResourceManager`, which reaches every resource, and one
+    /// of `other`'s resources are accessed with at least one side mutable.
+    fn overlaps_resources_of(&self, other: &SystemAccess) -> bool {
+        self.resource_manager_access().is_some_and(|manager_mutable| {
+            other.accesses.iter().any(|(key, mutable)| {
+                matches!(key, AccessKey::Resource(_)) && (manager_mutable || *mutable)
+            })
+        })
+    }
+
+    /// Returns whether the system accesses the `ResourceManager`, and if so, mutably or not.
+    fn resource_manager_access(&self) -> Option<bool> {
+        self.accesses
+            .get(&AccessKey::Manager(TypeId::of::<ResourceManager>()))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod access_test {
+    use std::any::TypeId;
+
+    use super::{AccessKey, SystemAccess};
+    use crate::core::resources::ResourceManager;
+
+    struct Position;
+    struct Velocity;
+    struct Frozen;
+
+    fn query(
+        fetched: &[(TypeId, bool)],
+        present_only: &[TypeId],
+        excluded: &[TypeId],
+    ) -> SystemAccess {
+        let mut access = SystemAccess::default();
+        access.add_query(
+            fetched.to_vec(),
+            Vec::new(),
+            present_only.to_vec(),
+            excluded.to_vec(),
+            "test",
+        );
+        access
+    }
+
+    #[test]
+    fn readers_of_the_same_component_do_not_conflict() {
+        let position = TypeId::of::<Position>();
+        let a = query(&[(position, false)], &[], &[]);
+        let b = query(&[(position, false), (TypeId::of::<Velocity>(), true)], &[], &[]);
+        assert!(!a.conflicts_with(&b));
+    }
+
+    #[test]
+    fn writer_conflicts_with_reader_unless_provably_disjoint() {
+        let position = TypeId::of::<Position>();
+        let frozen = TypeId::of::<Frozen>();
+        let writer = query(&[(position, true)], &[], &[frozen]);
+        assert!(writer.conflicts_with(&query(&[(position, false)], &[], &[])));
+        assert!(!writer.conflicts_with(&query(&[(position, false)], &[frozen], &[])));
+        // Change filters read the ticks the writer stamps.
+        assert!(writer.conflicts_with(&query(&[], &[position], &[])));
+    }
+
+    #[test]
+    fn resource_manager_overlaps_every_resource() {
+        let manager = TypeId::of::<ResourceManager>();
+        let resource = |mutable| {
+            let mut access = SystemAccess::default();
+            access.add(AccessKey::Resource(TypeId::of::<u32>()), mutable, "u32");
+            access
+        };
+        let mut reader = SystemAccess::default();
+        reader.add(AccessKey::Manager(manager), false, "ResourceManager");
+        assert!(reader.conflicts_with(&resource(true)));
+        assert!(resource(true).conflicts_with(&reader));
+        assert!(!reader.conflicts_with(&resource(false)));
+
+        let mut both = resource(true);
+        both.add(AccessKey::Manager(manager), false, "ResourceManager");
+        assert!(both.conflict().is_some());
+        let mut both = resource(false);
+        both.add(AccessKey::Manager(manager), false, "ResourceManager");
+        assert!(both.conflict().is_none());
+    }
+
+    #[test]
+    fn shared_entity_manager_conflicts_with_component_writers() {
+        let position = TypeId::of::<Position>();
+        let mut reader = SystemAccess::default();
+        reader.add_component_reader();
+        assert!(reader.conflicts_with(&query(&[(position, true)], &[], &[])));
+        assert!(query(&[(position, true)], &[], &[]).conflicts_with(&reader));
+        assert!(!reader.conflicts_with(&query(&[(position, false)], &[], &[])));
+
+        let mut both = query(&[(position, true)], &[], &[]);
+        both.add_component_reader();
+        assert!(both.conflict().is_some());
+        let mut both = query(&[(position, false)], &[], &[]);
+        both.add_component_reader();
+        assert!(both.conflict().is_none());
     }
 
-    /// Forgets every access recorded so far, so the next system
-    pub(crate) fn clear(&mut self) {
-        self.accesses.clear();
-        self.queries.clear();
+    #[test]
+    fn commands_conflict_with_any_data_access() {
+        let mut commands = SystemAccess::default();
+        commands.add_commands();
+        let mut resource = SystemAccess::default();
+        resource.add(AccessKey::Resource(TypeId::of::<u32>()), false, "u32");
+        assert!(commands.conflicts_with(&resource));
+        assert!(!commands.conflicts_with(&commands.clone()));
     }
 }
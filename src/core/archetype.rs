@@ -1,10 +1,11 @@
-use std::{cell::UnsafeCell, collections::HashMap};
+use std::{any::TypeId, collections::HashMap};
 
-use crate::core::component::ComponentColumn;
+use crate::core::component::{Column, ComponentColumn};
 
 use super::{
+    change_detection::{ComponentTicks, Mut},
     component::{BundleComponent, Component},
-    entity::EntityId,
+    entity::{Entity, EntityId},
     error::ArchetypeError,
 };
 
@@ -14,6 +15,11 @@ use super::{
 pub struct Archetype {
     pub(crate) components: HashMap<std::any::TypeId, Box<dyn ComponentColumn>>,
     pub(crate) entities: Vec<EntityId>,
+    /// Cached archetype-graph edges: the index of the archetype an entity moves to when the
+    /// keyed component type is added to it. Filled in lazily by the first such migration.
+    pub(crate) add_edges: HashMap<TypeId, usize>,
+    /// Like [`Archetype::add_edges`], for when the keyed component type is removed.
+    pub(crate) remove_edges: HashMap<TypeId, usize>,
 }
 
 /// An entity's components, detached from their archetype during a migration (e.g. via
@@ -21,12 +27,15 @@ pub struct Archetype {
 pub type MovedEntity = HashMap<std::any::TypeId, Box<dyn ComponentColumn>>;
 
 impl Archetype {
-    /// Creates a new archetype containing a single entity with the given component bundle.
-    pub fn new(entity_id: EntityId, components: impl BundleComponent) -> Self {
-        let components = components.create_map_components(entity_id);
+    /// Creates a new archetype containing a single entity with the given component bundle, added
+    /// at `change_tick`.
+    pub fn new(entity: Entity, components: impl BundleComponent, change_tick: u32) -> Self {
+        let components = components.create_map_components(entity, change_tick);
         Self {
             components,
-            entities: vec![entity_id],
+            entities: vec![entity.id],
+            add_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
         }
     }
     /// Creates a new archetype from an entity's components detached during a migration.
@@ -34,12 +43,20 @@ impl Archetype {
         Self {
             components,
             entities: vec![entity_id],
+            add_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
         }
     }
 
-    /// Adds an entity with the given component bundle to this (already-matching) archetype.
-    pub fn add_entity(&mut self, entity_id: EntityId, components: impl BundleComponent) {
-        for (type_id, column) in components.create_map_components(entity_id) {
+    /// Adds an entity with the given component bundle to this (already-matching) archetype, added
+    /// at `change_tick`, returning the row it was stored at.
+    pub fn add_entity(
+        &mut self,
+        entity: Entity,
+        components: impl BundleComponent,
+        change_tick: u32,
+    ) -> usize {
+        for (type_id, column) in components.create_map_components(entity, change_tick) {
             match self.components.get_mut(&type_id) {
                 Some(existing) => existing.merge_from(column),
                 None => {
@@ -47,12 +64,13 @@ impl Archetype {
                 }
             }
         }
-        self.entities.push(entity_id);
+        self.entities.push(entity.id);
+        self.entities.len() - 1
     }
 
     /// Re-inserts an entity (and its previously detached components) into this archetype
-    /// after a migration.
-    pub fn add_entity_migrated(&mut self, entity_id: EntityId, components: MovedEntity) {
+    /// after a migration, returning the row it was stored at.
+    pub fn add_entity_migrated(&mut self, entity_id: EntityId, components: MovedEntity) -> usize {
         for (type_id, column) in components {
             match self.components.get_mut(&type_id) {
                 Some(existing) => existing.merge_from(column),
@@ -62,43 +80,39 @@ impl Archetype {
             }
         }
         self.entities.push(entity_id);
+        self.entities.len() - 1
     }
 
-    /// Removes `entity_id` from this archetype and returns its detached components, so the
-    /// caller can insert them (possibly alongside new/removed component types) into another
-    /// archetype. Errors if the entity isn't in this archetype.
+    /// Removes the entity at `row` from this archetype and returns its id and detached
+    /// components, so the caller can insert them (possibly alongside new/removed component types)
+    /// into another archetype. The last entity is swapped into `row` to fill the gap. Errors if
+    /// `row` is out of bounds.
     pub fn migrate_entity_to_other_archetype(
         &mut self,
-        entity_id: EntityId,
+        row: usize,
     ) -> Result<(EntityId, MovedEntity), ArchetypeError> {
-        let index = self.entities.iter().position(|&x| x == entity_id);
-        match index {
-            Some(index) => {
-                let mut components = HashMap::new();
-                for (type_id, column) in self.components.iter_mut() {
-                    components.insert(*type_id, column.extract_single(index));
-                }
-                self.entities.swap_remove(index);
-                Ok((entity_id, components))
-            }
-            None => Err(ArchetypeError::EntityNotFound),
+        if row >= self.entities.len() {
+            return Err(ArchetypeError::EntityNotFound);
         }
+        let mut components = HashMap::new();
+        for (type_id, column) in self.components.iter_mut() {
+            components.insert(*type_id, column.extract_single(row));
+        }
+        let entity_id = self.entities.swap_remove(row);
+        Ok((entity_id, components))
     }
 
-    /// Removes `entity_id` and all its components from this archetype. Errors if the entity
-    /// isn't in this archetype.
-    pub fn remove_entity(&mut self, entity_id: EntityId) -> Result<(), ArchetypeError> {
-        let index = self.entities.iter().position(|&x| x == entity_id);
-        match index {
-            Some(index) => {
-                for column in self.components.values_mut() {
-                    column.swap_remove_drop(index);
-                }
-                self.entities.swap_remove(index);
-                Ok(())
-            }
-            None => Err(ArchetypeError::EntityNotFound),
+    /// Removes the entity at `row` and all its components from this archetype, swapping the last
+    /// entity into `row` to fill the gap. Errors if `row` is out of bounds.
+    pub fn remove_entity(&mut self, row: usize) -> Result<(), ArchetypeError> {
+        if row >= self.entities.len() {
+            return Err(ArchetypeError::EntityNotFound);
+        }
+        for column in self.components.values_mut() {
+            column.swap_remove_drop(row);
         }
+        self.entities.swap_remove(row);
+        Ok(())
     }
 
     /// Returns whether this archetype includes component type `type_id`.
@@ -106,31 +120,55 @@ impl Archetype {
         self.components.contains_key(&type_id)
     }
 
+    /// Returns this archetype's component types, sorted — the key it's registered under in
+    /// [`EntityManager`](super::entity_manager::EntityManager).
+    pub fn sorted_type_ids(&self) -> Vec<TypeId> {
+        let mut type_ids = self.components.keys().copied().collect::<Vec<_>>();
+        type_ids.sort();
+        type_ids
+    }
+
     /// Returns whether this archetype has no entities.
     pub fn is_empty(&self) -> bool {
         self.entities.is_empty()
     }
 
-    /// Returns a raw pointer to `entity_id`'s component `T` within this archetype, or `None`
-    /// if the entity or component type isn't present.
-    pub fn get_component<T: Component + 'static>(&self, entity_id: EntityId) -> Option<*const T> {
-        let local_index = self.entities.iter().position(|&id| id == entity_id)?;
+    /// Returns the typed column storing component `T`, or `None` if this archetype lacks `T`.
+    pub(crate) fn column<T: Component + 'static>(&self) -> Option<&Column<T>> {
         let column = self.components.get(&std::any::TypeId::of::<T>())?;
-        let list = column.as_any().downcast_ref::<UnsafeCell<Vec<T>>>()?;
-        let value = unsafe { (&*list.get()).get(local_index)? };
+        column.as_any().downcast_ref::<Column<T>>()
+    }
 
-        Some(value as *const _)
+    /// Returns the change ticks of component type `type_id` for the entity at `row`, or `None`
+    /// if the row or component type isn't present.
+    pub fn get_ticks(&self, type_id: TypeId, row: usize) -> Option<ComponentTicks> {
+        self.components.get(&type_id)?.ticks(row)
     }
 
-    /// Mutable counterpart to [`Archetype::get_component`].
-    pub fn get_component_mut<T: Component + 'static>(&self, entity_id: EntityId) -> Option<*mut T> {
-        let local_index = self.entities.iter().position(|&id| id == entity_id)?;
+    /// Returns a raw pointer to the component `T` of the entity at `row`, or `None` if the row
+    /// or component type isn't present.
+    pub fn get_component<T: Component + 'static>(&self, row: usize) -> Option<*const T> {
+        let column = self.column::<T>()?;
+        let value = unsafe { (&*column.data.get()).get(row)? };
 
-        let column = self.components.get(&std::any::TypeId::of::<T>())?;
-        let list = column.as_any().downcast_ref::<UnsafeCell<Vec<T>>>()?;
-        let value = unsafe { (&mut *list.get()).get_mut(local_index)? };
+        Some(value as *const _)
+    }
 
-        Some(value as *mut _)
+    /// Mutable counterpart to [`Archetype::get_component`]. Writing through the returned
+    /// [`Mut`] marks the component as changed at `change_tick`.
+    pub fn get_component_mut<T: Component + 'static>(
+        &mut self,
+        row: usize,
+        change_tick: u32,
+    ) -> Option<Mut<'_, T>> {
+        let column = self
+            .components
+            .get_mut(&std::any::TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()?;
+        let value = column.data.get_mut().get_mut(row)?;
+        let ticks = &mut column.ticks.get_mut()[row];
+        Some(Mut::new(value, &mut ticks.changed, change_tick))
     }
 }
 
@@ -146,8 +184,16 @@ fn test_archetype() {
     #[allow(dead_code)]
     pub struct Velocity(i32, i32);
 
-    let mut arch = Archetype::new(0, (Health(100), Position(0, 0), Velocity(0, 0)));
-    arch.add_entity(1, (Health(200), Position(1, 1), Velocity(1, 1)));
+    let mut arch = Archetype::new(
+        Entity::new(0, 0, 0),
+        (Health(100), Position(0, 0), Velocity(0, 0)),
+        1,
+    );
+    arch.add_entity(
+        Entity::new(1, 0, 0),
+        (Health(200), Position(1, 1), Velocity(1, 1)),
+        1,
+    );
 
     let (entity_id, moved_entity) = arch.migrate_entity_to_other_archetype(0).unwrap();
     assert_eq!(entity_id, 0);
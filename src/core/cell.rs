@@ -0,0 +1,153 @@
+use std::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Borrow state of an [`AtomicRefCell`] that's mutably borrowed.
+const WRITING: usize = usize::MAX;
+
+/// A thread-safe `RefCell`: hands out any number of shared borrows or a single mutable one,
+/// panicking on a conflicting borrow instead of blocking.
+///
+/// Every manager of a [`World`](super::world::World) lives in one of these, so the same
+/// managers can be reached from systems running in parallel on worker threads.
+pub struct AtomicRefCell<T: ?Sized> {
+    /// Number of live shared borrows, or [`WRITING`] while mutably borrowed.
+    borrow: AtomicUsize,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for AtomicRefCell<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for AtomicRefCell<T> {}
+
+impl<T> AtomicRefCell<T> {
+    /// Wraps `value`, initially unborrowed.
+    pub fn new(value: T) -> Self {
+        Self {
+            borrow: AtomicUsize::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized> AtomicRefCell<T> {
+    /// Immutably borrows the value until the returned guard is dropped.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    pub fn borrow(&self) -> AtomicRef<'_, T> {
+        let mut current = self.borrow.load(Ordering::Relaxed);
+        loop {
+            if current == WRITING || current == WRITING - 1 {
+                panic!("AtomicRefCell already mutably borrowed");
+            }
+            match self.borrow.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return AtomicRef { cell: self },
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Mutably borrows the value until the returned guard is dropped.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    pub fn borrow_mut(&self) -> AtomicRefMut<'_, T> {
+        if self
+            .borrow
+            .compare_exchange(0, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            panic!("AtomicRefCell already borrowed");
+        }
+        AtomicRefMut { cell: self }
+    }
+
+    /// Returns a raw pointer to the value, bypassing the borrow state. Used by the
+    /// [`SystemParam`](super::system::SystemParam) implementations, whose accesses are checked
+    /// up front instead (see [`SystemAccess`](super::access::SystemAccess)).
+    pub fn as_ptr(&self) -> *mut T {
+        self.value.get()
+    }
+}
+
+impl<T: Default> Default for AtomicRefCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// A shared borrow of an [`AtomicRefCell`], released on drop.
+pub struct AtomicRef<'a, T: ?Sized> {
+    cell: &'a AtomicRefCell<T>,
+}
+
+impl<T: ?Sized> Deref for AtomicRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for AtomicRef<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// A mutable borrow of an [`AtomicRefCell`], released on drop.
+pub struct AtomicRefMut<'a, T: ?Sized> {
+    cell: &'a AtomicRefCell<T>,
+}
+
+impl<T: ?Sized> Deref for AtomicRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for AtomicRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for AtomicRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.store(0, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod cell_test {
+    use super::AtomicRefCell;
+
+    #[test]
+    fn shared_borrows_coexist_and_release() {
+        let cell = AtomicRefCell::new(1);
+        {
+            let a = cell.borrow();
+            let b = cell.borrow();
+            assert_eq!(*a + *b, 2);
+        }
+        *cell.borrow_mut() += 1;
+        assert_eq!(*cell.borrow(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn mutable_borrow_while_borrowed_panics() {
+        let cell = AtomicRefCell::new(1);
+        let _shared = cell.borrow();
+        let _ = cell.borrow_mut();
+    }
+}
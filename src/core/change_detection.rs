@@ -0,0 +1,356 @@
+use std::{
+    any::TypeId,
+    ops::{Deref, DerefMut},
+};
+
+use super::{
+    access::{AccessKey, SystemAccess},
+    component::Component,
+    coordinator::Coordinator,
+    entity::Entity,
+    entity_manager::EntityManager,
+    system::{SystemMeta, SystemParam},
+};
+
+/// How many change ticks may pass between two runs of
+/// [`World::check_change_ticks`](super::world::World::check_change_ticks)'s clamping pass.
+pub(crate) const CHECK_TICK_THRESHOLD: u32 = 518_400_000;
+
+/// The oldest a stored change tick may get, in change ticks: older ones are moved up to this
+/// age by the periodic clamping pass, before the `u32` change tick wraps around and makes them
+/// look new again. Leaves room for two clamping passes between the age ticks are clamped to and
+/// the wraparound.
+pub const MAX_CHANGE_AGE: u32 = u32::MAX - (2 * CHECK_TICK_THRESHOLD - 1);
+
+/// Moves `tick` up to [`MAX_CHANGE_AGE`] ticks before `change_tick` if it's older than that.
+pub(crate) fn clamp_tick(tick: &mut u32, change_tick: u32) {
+    if change_tick.wrapping_sub(*tick) > MAX_CHANGE_AGE {
+        *tick = change_tick.wrapping_sub(MAX_CHANGE_AGE);
+    }
+}
+
+/// When a single component value was added and last written, as change ticks of the
+/// owning [`EntityManager`]. Stored row for row next to every component column, and read by the
+/// [`Added`](super::query::Added)/[`Changed`](super::query::Changed) query constraints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentTicks {
+    pub added: u32,
+    pub changed: u32,
+}
+
+impl ComponentTicks {
+    /// Ticks for a value inserted at `change_tick`. A new value counts as changed, too.
+    pub fn new(change_tick: u32) -> Self {
+        ComponentTicks {
+            added: change_tick,
+            changed: change_tick,
+        }
+    }
+
+    /// Clamps both ticks to at most [`MAX_CHANGE_AGE`] ticks before `change_tick`.
+    pub(crate) fn clamp(&mut self, change_tick: u32) {
+        clamp_tick(&mut self.added, change_tick);
+        clamp_tick(&mut self.changed, change_tick);
+    }
+}
+
+/// The change-tick window of one system run: anything stamped after `last_run` (the tick the
+/// system's previous run started at) is new to the system, and anything it changes is stamped
+/// with `this_run`.
+///
+/// The [`EntityManager`]'s change tick advances with every system run (runs of systems started
+/// together in parallel are handed consecutive ticks), so two runs never share a tick and each
+/// change is reported exactly once to every system watching for it. It wraps around once it
+/// reaches `u32::MAX`, so ticks are compared by their age relative to `this_run` rather than by
+/// value; see [`MAX_CHANGE_AGE`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemTicks {
+    pub last_run: u32,
+    pub this_run: u32,
+}
+
+impl SystemTicks {
+    /// The window of a first run starting at `this_run`: everything stamped up to then is new.
+    pub fn first_run(this_run: u32) -> Self {
+        SystemTicks {
+            last_run: this_run.wrapping_add(1),
+            this_run,
+        }
+    }
+
+    /// Returns whether something stamped at `tick` happened since the system last ran.
+    pub fn is_newer(&self, tick: u32) -> bool {
+        self.this_run.wrapping_sub(tick) < self.this_run.wrapping_sub(self.last_run)
+    }
+
+    /// Clamps both ticks to at most [`MAX_CHANGE_AGE`] ticks before `change_tick`.
+    pub(crate) fn clamp(&mut self, change_tick: u32) {
+        clamp_tick(&mut self.last_run, change_tick);
+        clamp_tick(&mut self.this_run, change_tick);
+    }
+}
+
+/// A component fetched mutably, by a [`Query`](super::query::Query) over `&mut T` or
+/// [`Entity::get_component_mut`](super::entity::Entity::get_component_mut). Reading it through
+/// `Deref` leaves it unchanged; only writing through `DerefMut` marks it as changed for
+/// [`Changed`](super::query::Changed), so merely fetching it doesn't.
+///
+/// ```
+/// # use dark_iron_ecs::core::query::Query;
+/// # struct Health(i32);
+/// fn clamp_health(mut q: Query<(&mut Health,)>) {
+///     for mut health in q.iter_mut() {
+///         if health.0 > 100 {
+///             health.0 = 100;
+///         }
+///     }
+/// }
+/// ```
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    changed: &'a mut u32,
+    change_tick: u32,
+}
+
+impl<'a, T> Mut<'a, T> {
+    /// Wraps `value`, whose `changed` tick is set to `change_tick` on its first write.
+    pub(crate) fn new(value: &'a mut T, changed: &'a mut u32, change_tick: u32) -> Self {
+        Mut {
+            value,
+            changed,
+            change_tick,
+        }
+    }
+
+    /// Returns the component as a plain mutable reference, marking it as changed.
+    pub fn into_inner(self) -> &'a mut T {
+        *self.changed = self.change_tick;
+        self.value
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        *self.changed = self.change_tick;
+        self.value
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A [`SystemParam`] listing the entities that lost their component `T` (via
+/// [`EntityManager::remove_component`] or by being removed outright) since the system last ran.
+///
+/// Removals are kept for two frames (see [`World::run_update`](super::world::World::run_update)),
+/// so every system running each frame sees each removal exactly once.
+///
+/// ```
+/// # use dark_iron_ecs::core::change_detection::RemovedComponents;
+/// # struct Health(i32);
+/// fn on_health_removed(removed: RemovedComponents<Health>) {
+///     for entity in removed.iter() {
+///         println!("entity {} lost its Health", entity.id);
+///     }
+/// }
+/// ```
+pub struct RemovedComponents<T: Component> {
+    entities: Vec<Entity>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Component> RemovedComponents<T> {
+    /// Iterates the entities that lost their `T`, oldest removal first. They may have been
+    /// removed entirely, so their handles may be stale.
+    pub fn iter(&self) -> std::slice::Iter<'_, Entity> {
+        self.entities.iter()
+    }
+
+    /// Returns how many removals of `T` are new to this system.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns whether no `T` was removed since this system last ran.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+impl<T: Component> IntoIterator for RemovedComponents<T> {
+    type Item = Entity;
+    type IntoIter = std::vec::IntoIter<Entity>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.into_iter()
+    }
+}
+
+impl<T: Component> SystemParam for RemovedComponents<T> {
+    fn access(access: &mut SystemAccess) {
+        access.add(
+            AccessKey::Manager(TypeId::of::<EntityManager>()),
+            false,
+            "EntityManager (via RemovedComponents)",
+        );
+    }
+
+    fn get_param(coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        let ticks = meta.ticks;
+        let entity_manager = unsafe { &*coordinator.get_entity_manager_mut() };
+        let entities = entity_manager
+            .removed_components
+            .get(&TypeId::of::<T>())
+            .map(|removed| {
+                removed
+                    .iter()
+                    .filter(|(_, tick)| ticks.is_newer(*tick))
+                    .map(|(entity, _)| *entity)
+                    .collect()
+            })
+            .unwrap_or_default();
+        RemovedComponents {
+            entities,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod change_detection_test {
+    use crate::core::{
+        command::Commands,
+        query::{Added, Changed, Query},
+        system::SystemSchedule,
+        world::World,
+    };
+
+    use super::{CHECK_TICK_THRESHOLD, RemovedComponents};
+
+    struct Health(i32);
+    #[allow(dead_code)]
+    struct Name(&'static str);
+
+    fn count_removed(removed: RemovedComponents<Health>, mut commands: Commands) {
+        commands.add_resource(removed.len());
+    }
+
+    #[test]
+    fn removed_components_are_reported_once_then_forgotten() {
+        let mut world = World::new();
+        let hurt = world.create_entity_with_id((Health(1), Name("hurt")));
+        let dead = world.create_entity_with_id((Health(0), Name("dead")));
+        world.add_system(SystemSchedule::Update, count_removed);
+
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 0);
+
+        world.remove_component::<Health>(hurt).remove_entity(dead);
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 2);
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 0);
+
+        world.run_update();
+        let entity_manager = world.entity_manager.borrow();
+        assert!(
+            entity_manager
+                .removed_components
+                .values()
+                .all(|removed| removed.is_empty()),
+            "removals older than the previous frame must be dropped"
+        );
+    }
+
+    #[test]
+    fn removal_by_an_earlier_system_in_the_frame_is_seen_by_a_later_one() {
+        fn strip_health(
+            q: crate::core::query::Query<(&crate::core::entity::Entity, &Health)>,
+            mut commands: Commands,
+        ) {
+            for (entity, health) in q.fetch() {
+                if health.0 <= 0 {
+                    commands.remove_component::<Health>(*entity);
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Health(0), Name("a")))
+            .create_entity((Health(3), Name("b")))
+            .add_systems(SystemSchedule::Update, (strip_health, count_removed));
+
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 1);
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 0);
+    }
+
+    fn report_new(
+        added: Query<(&Health,), Added<Health>>,
+        changed: Query<(&Health,), Changed<Health>>,
+        mut commands: Commands,
+    ) {
+        commands.add_resource((added.fetch().len(), changed.fetch().len()));
+    }
+
+    fn seen(world: &World) -> (usize, usize) {
+        *world.get_resource::<(usize, usize)>().unwrap()
+    }
+
+    #[test]
+    fn changes_are_still_reported_once_the_change_tick_wraps_around() {
+        let mut world = World::new();
+        world.entity_manager.borrow_mut().change_tick = u32::MAX - 1;
+        let mut entity = world.create_entity_with_id((Health(1),));
+        world.add_system(SystemSchedule::Update, report_new);
+
+        world.run_update();
+        assert_eq!(seen(&world), (1, 1));
+        world.run_update();
+        assert_eq!(seen(&world), (0, 0));
+        assert!(
+            world.entity_manager.borrow().change_tick < 10,
+            "the tick must have wrapped"
+        );
+
+        entity.get_component_mut::<Health>(&mut world).unwrap().0 = 2;
+        world.create_entity((Health(5),));
+        world.run_update();
+        assert_eq!(seen(&world), (1, 2));
+        world.run_update();
+        assert_eq!(seen(&world), (0, 0));
+    }
+
+    #[test]
+    fn old_ticks_are_clamped_so_they_never_look_new_again() {
+        let mut world = World::new();
+        world.create_entity((Health(1),));
+        world.add_system(SystemSchedule::Update, report_new);
+
+        world.run_update();
+        assert_eq!(seen(&world), (1, 1));
+        // Far enough for the change tick to wrap past the entity's ticks.
+        for _ in 0..9 {
+            let mut entity_manager = world.entity_manager.borrow_mut();
+            entity_manager.change_tick = entity_manager
+                .change_tick
+                .wrapping_add(CHECK_TICK_THRESHOLD);
+            drop(entity_manager);
+            world.run_update();
+            assert_eq!(seen(&world), (0, 0));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::{Arc, Mutex};
+
+use super::{
+    access::SystemAccess,
+    component::{BundleComponent, Component},
+    coordinator::Coordinator,
+    entity::Entity,
+    error::QueryError,
+    system::{SystemMeta, SystemParam},
+    world::World,
+};
+
+type Command = Box<dyn FnOnce(&World) -> Result<(), QueryError> + Send>;
+
+/// Structural changes recorded through [`Commands`] while a system runs, waiting for the next
+/// sync point. Each system has its own, kept in its [`SystemMeta`].
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    fn push(
+        &mut self,
+        command: impl FnOnce(&World) -> Result<(), QueryError> + Send + 'static,
+    ) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Moves every command of `other` to the end of this queue.
+    pub(crate) fn append(&mut self, other: &mut CommandQueue) {
+        self.commands.append(&mut other.commands);
+    }
+
+    /// Applies every command queued so far to `world`, in the order they were recorded, passing
+    /// the error of each one that fails (e.g. targeting a removed entity) to `on_error`.
+    /// Called by [`SystemManager`](super::system::SystemManager) once the batch of systems the
+    /// recording one ran in returns, so no query handed to them is alive anymore.
+    pub(crate) fn apply(
+        queue: &Mutex<CommandQueue>,
+        world: &World,
+        mut on_error: impl FnMut(QueryError),
+    ) {
+        let commands = std::mem::take(&mut queue.lock().unwrap().commands);
+        for command in commands {
+            if let Err(error) = command(world) {
+                on_error(error);
+            }
+        }
+    }
+}
+
+/// A [`SystemParam`] that records structural changes (spawning/removing entities,
+/// adding/removing components, adding resources) instead of applying them immediately.
+///
+/// The changes are applied once the system returns, so unlike `&mut EntityManager`, `Commands`
+/// never conflicts with a [`Query`](super::query::Query) in the same system and never invalidates
+/// the references it handed out. They are applied before any later system that touches entities
+/// or resources runs, so such systems are never run in parallel with this one. A command that
+/// fails by then (e.g. one targeting an entity removed meanwhile) is passed to the error handler
+/// (see [`World::set_error_handler`]) as a failure of the recording system.
+///
+/// ```
+/// # use dark_iron_ecs::core::{command::Commands, entity::Entity, query::Query};
+/// # struct Health(i32);
+/// fn despawn_dead(q: Query<(&Health, &Entity)>, mut commands: Commands) {
+///     for (health, entity) in q.fetch() {
+///         if health.0 <= 0 {
+///             commands.remove_entity(*entity);
+///         }
+///     }
+/// }
+/// ```
+pub struct Commands {
+    queue: Arc<Mutex<CommandQueue>>,
+}
+
+impl Commands {
+    /// Queues spawning a new entity with the given bundle of components. Its [`Entity`] id isn't
+    /// known until the command is applied. Returns `&mut Self` for chaining.
+    pub fn create_entity(
+        &mut self,
+        components: impl BundleComponent + Send + 'static,
+    ) -> &mut Self {
+        self.queue.lock().unwrap().push(move |world| {
+            world.entity_manager.borrow_mut().create_entity(components);
+            Ok(())
+        });
+        self
+    }
+
+    /// Queues removing `entity` and all of its components. Fails if `entity` no longer exists by
+    /// the time the command is applied. Returns `&mut Self` for chaining.
+    pub fn remove_entity(&mut self, entity: Entity) -> &mut Self {
+        self.queue
+            .lock()
+            .unwrap()
+            .push(move |world| world.entity_manager.borrow_mut().remove_entity(entity));
+        self
+    }
+
+    /// Queues adding (or replacing) component `T` on `entity`. Fails if `entity` no longer
+    /// exists by the time the command is applied. Returns `&mut Self` for chaining.
+    pub fn add_component_to_entity<T: 'static + Component>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> &mut Self {
+        self.queue.lock().unwrap().push(move |world| {
+            world
+                .entity_manager
+                .borrow_mut()
+                .add_component_to_entity(entity, component)
+        });
+        self
+    }
+
+    /// Queues removing component `T` from `entity`. Fails if `entity` no longer exists, or has
+    /// no `T`, by the time the command is applied. Returns `&mut Self` for chaining.
+    pub fn remove_component<T: 'static + Component>(&mut self, entity: Entity) -> &mut Self {
+        self.queue.lock().unwrap().push(move |world| {
+            world
+                .entity_manager
+                .borrow_mut()
+                .remove_component::<T>(entity)
+        });
+        self
+    }
+
+    /// Queues inserting `resource`, replacing any existing resource of the same type. Returns
+    /// `&mut Self` for chaining.
+    pub fn add_resource<T: 'static + Send + Sync>(&mut self, resource: T) -> &mut Self {
+        self.queue.lock().unwrap().push(move |world| {
+            world.resources.borrow_mut().add(resource);
+            Ok(())
+        });
+        self
+    }
+}
+
+impl SystemParam for Commands {
+    fn access(access: &mut SystemAccess) {
+        // Recording a command touches no ECS data until the sync point, so it can't conflict
+        // with another parameter of the same system — only with later systems.
+        access.add_commands();
+    }
+
+    fn get_param(_coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        Commands {
+            queue: meta.command_queue.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod command_test {
+    use crate::core::{
+        entity::Entity,
+        error_handler::{SystemErrors, collect_errors},
+        query::{Query, Without},
+        system::SystemSchedule,
+        world::World,
+    };
+
+    use super::Commands;
+
+    struct Health(i32);
+    struct Burning;
+
+    #[test]
+    fn system_can_despawn_entities_it_is_iterating_over() {
+        fn despawn_dead(q: Query<(&Health, &Entity)>, mut commands: Commands) {
+            for (health, entity) in q.fetch() {
+                if health.0 <= 0 {
+                    commands.remove_entity(*entity);
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Health(0),))
+            .create_entity((Health(5),))
+            .create_entity((Health(-3),))
+            .add_system(SystemSchedule::Update, despawn_dead);
+        world.run_update();
+
+        let remaining = world.create_query::<&Health>().fetch();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].0, 5);
+    }
+
+    #[test]
+    fn commands_are_applied_before_the_next_system_runs() {
+        fn ignite(q: Query<(&Entity,), Without<&Burning>>, mut commands: Commands) {
+            for entity in q.fetch() {
+                commands.add_component_to_entity(*entity, Burning);
+            }
+            commands.create_entity((Health(1), Burning));
+            commands.add_resource(7u32);
+        }
+        fn count_burning(q: Query<(&Burning,)>, mut commands: Commands) {
+            commands.add_resource(q.fetch().len());
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Health(10),))
+            .add_systems(SystemSchedule::Update, (ignite, count_burning));
+        world.run_update();
+
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 2);
+        assert_eq!(*world.get_resource::<u32>().unwrap(), 7);
+    }
+
+    #[test]
+    fn failed_commands_reach_the_error_handler() {
+        fn despawn_twice(q: Query<(&Entity,)>, mut commands: Commands) {
+            for entity in q.fetch() {
+                commands
+                    .remove_entity(*entity)
+                    .remove_component::<Health>(*entity);
+            }
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Health(1),))
+            .set_error_handler(collect_errors)
+            .add_system(SystemSchedule::Update, despawn_twice);
+        world.run_update();
+
+        let errors = world.get_resource::<SystemErrors>().unwrap();
+        assert_eq!(errors.0.len(), 1);
+        assert!(errors.0[0].system.ends_with("despawn_twice"));
+        assert!(errors.0[0].error.to_string().starts_with("QueryError"));
+    }
+}
@@ -1,13 +1,14 @@
 use std::{any::Any, cell::UnsafeCell, collections::HashMap};
 
-use super::entity::EntityId;
+use super::{change_detection::ComponentTicks, entity::Entity};
 
-/// Marker trait for any `'static` type usable as a component. Blanket-implemented for every
-/// such type, so no manual `impl` is needed.
-pub trait Component: Any {}
-impl<T: Any> Component for T {}
+/// Marker trait for any `'static`, thread-safe type usable as a component. Blanket-implemented
+/// for every such type, so no manual `impl` is needed. Components must be `Send + Sync` since
+/// systems accessing them may run on worker threads.
+pub trait Component: Any + Send + Sync {}
+impl<T: Any + Send + Sync> Component for T {}
 
-pub trait ComponentColumn: Any {
+pub trait ComponentColumn: Any + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
     fn into_any(self: Box<Self>) -> Box<dyn Any>;
@@ -17,6 +18,13 @@ pub trait ComponentColumn: Any {
         self.len() == 0
     }
 
+    /// Returns the change ticks of the value at `index`, or `None` if it's out of bounds.
+    fn ticks(&self, index: usize) -> Option<ComponentTicks>;
+
+    /// Clamps the change ticks of every value to at most
+    /// [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before `change_tick`.
+    fn clamp_ticks(&mut self, change_tick: u32);
+
     fn extract_single(&mut self, index: usize) -> Box<dyn ComponentColumn>;
 
     fn merge_from(&mut self, other: Box<dyn ComponentColumn>);
@@ -24,7 +32,34 @@ pub trait ComponentColumn: Any {
     fn swap_remove_drop(&mut self, index: usize);
 }
 
-impl<T: Component + 'static> ComponentColumn for UnsafeCell<Vec<T>> {
+/// Storage for one component type in one archetype: the values, plus the [`ComponentTicks`] of
+/// each one, row for row.
+pub struct Column<T> {
+    pub(crate) data: UnsafeCell<Vec<T>>,
+    pub(crate) ticks: UnsafeCell<Vec<ComponentTicks>>,
+}
+
+// Columns are shared by systems running in parallel; the scheduler never runs a system mutably
+// accessing a column alongside another accessing it (see `SystemAccess::conflicts_with`).
+unsafe impl<T: Send + Sync> Sync for Column<T> {}
+
+impl<T> Column<T> {
+    /// Creates a column holding `values`, all added (and changed) at `change_tick`.
+    pub(crate) fn from_values(values: Vec<T>, change_tick: u32) -> Self {
+        let ticks = vec![ComponentTicks::new(change_tick); values.len()];
+        Self {
+            data: UnsafeCell::new(values),
+            ticks: UnsafeCell::new(ticks),
+        }
+    }
+
+    /// Creates a single-row column holding `value`, added (and changed) at `change_tick`.
+    pub(crate) fn new(value: T, change_tick: u32) -> Self {
+        Self::from_values(vec![value], change_tick)
+    }
+}
+
+impl<T: Component + 'static> ComponentColumn for Column<T> {
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -38,28 +73,42 @@ impl<T: Component + 'static> ComponentColumn for UnsafeCell<Vec<T>> {
     }
 
     fn len(&self) -> usize {
-        unsafe { (*self.get()).len() }
+        unsafe { (*self.data.get()).len() }
+    }
+
+    fn ticks(&self, index: usize) -> Option<ComponentTicks> {
+        unsafe { (&*self.ticks.get()).get(index).copied() }
+    }
+
+    fn clamp_ticks(&mut self, change_tick: u32) {
+        for ticks in self.ticks.get_mut() {
+            ticks.clamp(change_tick);
+        }
     }
 
     fn extract_single(&mut self, index: usize) -> Box<dyn ComponentColumn> {
-        let value = unsafe { (*self.get()).swap_remove(index) };
-        Box::new(UnsafeCell::new(vec![value]))
+        let value = self.data.get_mut().swap_remove(index);
+        let ticks = self.ticks.get_mut().swap_remove(index);
+        Box::new(Column {
+            data: UnsafeCell::new(vec![value]),
+            ticks: UnsafeCell::new(vec![ticks]),
+        })
     }
 
     fn merge_from(&mut self, other: Box<dyn ComponentColumn>) {
-        let mut other_vec = other
+        let other = other
             .into_any()
-            .downcast::<UnsafeCell<Vec<T>>>()
-            .expect("ComponentColumn::merge_from: expected UnsafeCell<Vec<T>>")
-            .into_inner();
+            .downcast::<Column<T>>()
+            .expect("ComponentColumn::merge_from: expected Column<T>");
+        let Column { data, ticks } = *other;
 
-        unsafe { (*self.get()).append(&mut other_vec) };
+        self.data.get_mut().append(&mut data.into_inner());
+        self.ticks.get_mut().append(&mut ticks.into_inner());
     }
 
     fn swap_remove_drop(&mut self, index: usize) {
-        unsafe {
-            (*self.get()).swap_remove(index);
-        }
+        self.data.get_mut().swap_remove(index);
+        self.ticks.get_mut().swap_remove(index);
     }
 }
 
@@ -69,7 +118,8 @@ impl<T: Component + 'static> ComponentColumn for UnsafeCell<Vec<T>> {
 pub trait BundleComponent {
     fn create_map_components(
         self,
-        entity_id: EntityId,
+        entity: Entity,
+        change_tick: u32,
     ) -> HashMap<std::any::TypeId, Box<dyn ComponentColumn>>;
     fn get_types_id(&self) -> Vec<std::any::TypeId>;
 }
@@ -77,16 +127,16 @@ pub trait BundleComponent {
 macro_rules! impl_bundle_component {
     // Base case: Implement for a single element tuple
     ( $head:ident ) => {
-        impl< $head: 'static > BundleComponent for ($head,) {
-            fn create_map_components(self, entity_id: EntityId) -> HashMap<std::any::TypeId, Box<dyn ComponentColumn>> {
+        impl< $head: Component > BundleComponent for ($head,) {
+            fn create_map_components(self, entity: Entity, change_tick: u32) -> HashMap<std::any::TypeId, Box<dyn ComponentColumn>> {
                 let mut map = HashMap::new();
                 map.insert(
                     std::any::TypeId::of::<$head>(),
-                    (Box::new(UnsafeCell::new(vec![self.0])) as Box<dyn ComponentColumn>),
+                    (Box::new(Column::new(self.0, change_tick)) as Box<dyn ComponentColumn>),
                 );
                 map.insert(
                     std::any::TypeId::of::<super::entity::Entity>(),
-                    Box::new(UnsafeCell::new(vec![super::entity::Entity::new(entity_id, 0)])) as Box<dyn ComponentColumn>,
+                    Box::new(Column::new(entity, change_tick)) as Box<dyn ComponentColumn>,
                 );
                 map
             }
@@ -101,26 +151,26 @@ macro_rules! impl_bundle_component {
     // Recursive case: Implement for tuples with more than one element
     ( $head:ident, $($tail:ident),+ ) => {
         impl_bundle_component!($($tail),+);
-        impl< $head: 'static, $($tail: 'static ),* > BundleComponent for ($head, $($tail),*) {
+        impl< $head: Component, $($tail: Component ),* > BundleComponent for ($head, $($tail),*) {
 
             #[allow(non_snake_case)]
             #[allow(unused_variables)]
-            fn create_map_components(self,  entity_id: EntityId) -> HashMap<std::any::TypeId, Box<dyn ComponentColumn>> {
+            fn create_map_components(self, entity: Entity, change_tick: u32) -> HashMap<std::any::TypeId, Box<dyn ComponentColumn>> {
                 let mut map = HashMap::new();
                 let ($head, $($tail),*) = self;
                 map.insert(
                     std::any::TypeId::of::<$head>(),
-                    (Box::new(UnsafeCell::new(vec![$head])) as Box<dyn ComponentColumn>),
+                    (Box::new(Column::new($head, change_tick)) as Box<dyn ComponentColumn>),
                 );
                 $(
                     map.insert(
                         std::any::TypeId::of::<$tail>(),
-                        (Box::new(UnsafeCell::new(vec![$tail])) as Box<dyn ComponentColumn>),
+                        (Box::new(Column::new($tail, change_tick)) as Box<dyn ComponentColumn>),
                     );
                 )*
                 map.insert(
                     std::any::TypeId::of::<super::entity::Entity>(),
-                    (Box::new(UnsafeCell::new(vec![super::entity::Entity::new(entity_id, 0)])) as Box<dyn ComponentColumn>),
+                    (Box::new(Column::new(entity, change_tick)) as Box<dyn ComponentColumn>),
                 );
 
                 map
@@ -143,31 +193,31 @@ mod component_column_tests {
     use super::*;
     #[test]
     fn component_column_extract_and_merge_roundtrip() {
-        let mut boxed: Box<dyn ComponentColumn> = Box::new(UnsafeCell::new(vec![10i32, 20, 30]));
+        let mut boxed: Box<dyn ComponentColumn> =
+            Box::new(Column::from_values(vec![10i32, 20, 30], 1));
 
         let extracted = boxed.extract_single(0); // swap_remove(0) -> column becomes [30, 20]
 
-        let remaining = boxed
-            .as_any()
-            .downcast_ref::<UnsafeCell<Vec<i32>>>()
-            .unwrap();
-        assert_eq!(unsafe { &*remaining.get() }, &vec![30, 20]);
+        let remaining = boxed.as_any().downcast_ref::<Column<i32>>().unwrap();
+        assert_eq!(unsafe { &*remaining.data.get() }, &vec![30, 20]);
 
-        let mut target: Box<dyn ComponentColumn> = Box::new(UnsafeCell::new(vec![99i32]));
+        let mut target: Box<dyn ComponentColumn> = Box::new(Column::new(99i32, 5));
         target.merge_from(extracted);
 
-        let merged = target
-            .as_any()
-            .downcast_ref::<UnsafeCell<Vec<i32>>>()
-            .unwrap();
-        assert_eq!(unsafe { &*merged.get() }, &vec![99, 10]);
+        let merged = target.as_any().downcast_ref::<Column<i32>>().unwrap();
+        assert_eq!(unsafe { &*merged.data.get() }, &vec![99, 10]);
+        // Ticks travel with their value.
+        assert_eq!(target.ticks(0), Some(ComponentTicks::new(5)));
+        assert_eq!(target.ticks(1), Some(ComponentTicks::new(1)));
     }
 
     #[test]
     fn component_column_swap_remove_drop_does_not_panic_and_shrinks_len() {
-        let mut boxed: Box<dyn ComponentColumn> = Box::new(UnsafeCell::new(vec!["a", "b", "c"]));
+        let mut boxed: Box<dyn ComponentColumn> =
+            Box::new(Column::from_values(vec!["a", "b", "c"], 1));
         assert_eq!(boxed.len(), 3);
         boxed.swap_remove_drop(1);
         assert_eq!(boxed.len(), 2);
+        assert_eq!(boxed.ticks(2), None);
     }
 }
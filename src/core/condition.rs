@@ -0,0 +1,312 @@
+use std::time::Duration;
+
+use super::{
+    coordinator::Coordinator,
+    error::SystemError,
+    local::Local,
+    resources::Res,
+    state::{State, States},
+    system::{BoxedSystem, System},
+    time::Time,
+};
+
+/// A run condition in the type-erased form stored with a system: a read-only system returning
+/// `bool`, or a combination of them. Built by [`Condition::into_condition`].
+pub struct BoxedCondition {
+    kind: ConditionKind,
+}
+
+enum ConditionKind {
+    System(Box<BoxedSystem<bool>>),
+    And(Box<BoxedCondition>, Box<BoxedCondition>),
+    Or(Box<BoxedCondition>, Box<BoxedCondition>),
+    Not(Box<BoxedCondition>),
+}
+
+impl BoxedCondition {
+    /// Returns an error naming `system` unless every system making up the condition is
+    /// read-only and free of conflicts between its own parameters.
+    pub(crate) fn validate(&self, system: &'static str) -> Result<(), SystemError> {
+        match &self.kind {
+            ConditionKind::System(condition) => {
+                if let Some(conflict) = condition.access().conflict() {
+                    return Err(SystemError::ConflictingAccess {
+                        system: condition.name(),
+                        conflict: conflict.to_owned(),
+                    });
+                }
+                if let Some((_, input)) = condition.access().input() {
+                    return Err(SystemError::UnpipedInput {
+                        system: condition.name(),
+                        input,
+                    });
+                }
+                if !condition.access().is_read_only() {
+                    return Err(SystemError::MutableCondition {
+                        system,
+                        condition: condition.name(),
+                    });
+                }
+                Ok(())
+            }
+            ConditionKind::And(a, b) | ConditionKind::Or(a, b) => {
+                a.validate(system)?;
+                b.validate(system)
+            }
+            ConditionKind::Not(condition) => condition.validate(system),
+        }
+    }
+
+    /// Clamps the change ticks of every system making up the condition to at most
+    /// [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before `change_tick`.
+    pub(crate) fn clamp_ticks(&mut self, change_tick: u32) {
+        match &mut self.kind {
+            ConditionKind::System(condition) => condition.meta.clamp_ticks(change_tick),
+            ConditionKind::And(a, b) | ConditionKind::Or(a, b) => {
+                a.clamp_ticks(change_tick);
+                b.clamp_ticks(change_tick);
+            }
+            ConditionKind::Not(condition) => condition.clamp_ticks(change_tick),
+        }
+    }
+
+    /// Evaluates the condition, short-circuiting `and`/`or`. Each system making up the
+    /// condition that actually runs gets a fresh change tick from `change_tick`.
+    pub(crate) fn evaluate(&mut self, coordinator: &Coordinator, change_tick: &mut u32) -> bool {
+        match &mut self.kind {
+            ConditionKind::System(condition) => {
+                condition.meta.begin_run(change_tick);
+                condition.run(coordinator)
+            }
+            ConditionKind::And(a, b) => {
+                a.evaluate(coordinator, change_tick) && b.evaluate(coordinator, change_tick)
+            }
+            ConditionKind::Or(a, b) => {
+                a.evaluate(coordinator, change_tick) || b.evaluate(coordinator, change_tick)
+            }
+            ConditionKind::Not(condition) => !condition.evaluate(coordinator, change_tick),
+        }
+    }
+}
+
+/// Implemented for every read-only system returning `bool` (and for [`BoxedCondition`]),
+/// letting it gate another system via
+/// [`IntoSystemConfig::run_if`](super::system::IntoSystemConfig::run_if):
+///
+/// ```
+/// # use dark_iron_ecs::core::{condition::{Condition, not, resource_exists}, world::World};
+/// # use dark_iron_ecs::core::system::{IntoSystemConfig, SystemSchedule};
+/// # use dark_iron_ecs::core::entity_manager::EntityManager;
+/// struct Paused;
+/// struct Menu;
+/// # fn gameplay(_: &EntityManager) {}
+/// World::new().add_system(
+///     SystemSchedule::Update,
+///     gameplay.run_if(not(resource_exists::<Paused>).and(not(resource_exists::<Menu>))),
+/// );
+/// ```
+///
+/// Conditions are evaluated on the calling thread right before the system's batch runs, so
+/// they see the effects of every earlier batch. A system accessing anything mutably (including
+/// through `ResMut<T>` or `Commands`) is rejected as a condition; read resources through
+/// [`Res<T>`](super::resources::Res) instead.
+pub trait Condition<P>: Sized {
+    fn into_condition(self) -> BoxedCondition;
+
+    /// Holds when both conditions hold; `other` is only evaluated if `self` holds.
+    fn and<Q>(self, other: impl Condition<Q>) -> BoxedCondition {
+        BoxedCondition {
+            kind: ConditionKind::And(
+                Box::new(self.into_condition()),
+                Box::new(other.into_condition()),
+            ),
+        }
+    }
+
+    /// Holds when either condition holds; `other` is only evaluated if `self` doesn't.
+    fn or<Q>(self, other: impl Condition<Q>) -> BoxedCondition {
+        BoxedCondition {
+            kind: ConditionKind::Or(
+                Box::new(self.into_condition()),
+                Box::new(other.into_condition()),
+            ),
+        }
+    }
+}
+
+impl<P, F> Condition<P> for F
+where
+    F: System<P, Out = bool> + Send + Sync + 'static,
+{
+    fn into_condition(self) -> BoxedCondition {
+        BoxedCondition {
+            kind: ConditionKind::System(Box::new(BoxedSystem::new(self))),
+        }
+    }
+}
+
+impl Condition<BoxedCondition> for BoxedCondition {
+    fn into_condition(self) -> BoxedCondition {
+        self
+    }
+}
+
+/// Holds when `condition` doesn't.
+pub fn not<P>(condition: impl Condition<P>) -> BoxedCondition {
+    BoxedCondition {
+        kind: ConditionKind::Not(Box::new(condition.into_condition())),
+    }
+}
+
+/// Holds while a resource of type `T` exists.
+pub fn resource_exists<T: 'static>(resource: Option<Res<T>>) -> bool {
+    resource.is_some()
+}
+
+/// Holds while state `S` is `state`. False if `S` wasn't added via
+/// [`World::init_state`](super::world::World::init_state).
+pub fn in_state<S: States>(
+    state: S,
+) -> impl Fn(Option<Res<State<S>>>) -> bool + Clone + Send + Sync {
+    move |current: Option<Res<State<S>>>| current.is_some_and(|current| *current.get() == state)
+}
+
+/// Holds once every `seconds` of [`Time`]: the first time at least `seconds` have elapsed, then
+/// again each time another `seconds` have. Scaled and paused along with [`Time`]. The timer is
+/// kept in a [`Local`], so every system a [`SystemBundle::run_if`] copy of it gates keeps its
+/// own.
+///
+/// [`SystemBundle::run_if`]: super::system::SystemBundle::run_if
+pub fn on_timer(
+    seconds: f32,
+) -> impl Fn(Option<Res<Time>>, Local<Duration>) -> bool + Clone + Send + Sync + 'static {
+    let period = Duration::from_secs_f32(seconds);
+    // `fired_until` is the elapsed time up to which every period was already fired for.
+    move |time: Option<Res<Time>>, mut fired_until: Local<Duration>| {
+        let Some(time) = time else {
+            return false;
+        };
+        if time.elapsed() < *fired_until + period {
+            return false;
+        }
+        while *fired_until + period <= time.elapsed() && !period.is_zero() {
+            *fired_until += period;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod condition_test {
+    use super::{Condition, not, on_timer, resource_exists};
+    use crate::core::{
+        error::SystemError,
+        resources::Resource,
+        system::{IntoSystemConfig, System, SystemBundle, SystemSchedule},
+        world::World,
+    };
+
+    struct Paused;
+
+    fn count(mut runs: Resource<u32>) {
+        *runs += 1;
+    }
+
+    fn runs(world: &World) -> u32 {
+        *world.get_resource::<u32>().unwrap()
+    }
+
+    #[test]
+    fn systems_only_run_while_their_conditions_hold() {
+        let mut world = World::new();
+        world.add_resource(0u32).add_system(
+            SystemSchedule::Update,
+            count.run_if(not(resource_exists::<Paused>)),
+        );
+
+        world.run_update();
+        world.add_resource(Paused);
+        world.run_update();
+        assert_eq!(runs(&world), 1);
+    }
+
+    #[test]
+    fn combined_conditions_gate_systems_and_bundles() {
+        let mut world = World::new();
+        world
+            .add_resource(0u32)
+            .add_systems(
+                SystemSchedule::Update,
+                (count, count).chain().run_if(resource_exists::<u32>),
+            )
+            .add_system(
+                SystemSchedule::Update,
+                count.run_if(resource_exists::<Paused>.or(resource_exists::<u32>)),
+            )
+            .add_system(
+                SystemSchedule::Update,
+                count.run_if(resource_exists::<u32>.and(resource_exists::<Paused>)),
+            );
+
+        world.run_update();
+        assert_eq!(runs(&world), 3);
+    }
+
+    #[test]
+    fn on_timer_fires_once_per_period_of_scaled_time() {
+        let mut world = World::new();
+        world
+            .add_resource(0u32)
+            .add_system(SystemSchedule::Update, count.run_if(on_timer(1.0)));
+
+        let mut fired = Vec::new();
+        for _ in 0..6 {
+            world.tick(0.4);
+            fired.push(runs(&world));
+        }
+        // Fires at 1.2 s and 2.0 s of elapsed time.
+        assert_eq!(fired, [0, 0, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn on_timer_gates_each_system_of_a_bundle() {
+        let mut world = World::new();
+        world.add_resource(0u32).add_systems(
+            SystemSchedule::Update,
+            (count, count).chain().run_if(on_timer(1.0)),
+        );
+
+        let mut fired = Vec::new();
+        for _ in 0..6 {
+            world.tick(0.4);
+            fired.push(runs(&world));
+        }
+        assert_eq!(fired, [0, 0, 2, 2, 4, 4]);
+    }
+
+    #[test]
+    fn built_in_conditions_only_read_their_resource() {
+        let writes_count = System::access(&count);
+        let checks_paused = System::access(&resource_exists::<Paused>);
+        assert!(checks_paused.is_read_only());
+        assert!(!checks_paused.conflicts_with(&writes_count));
+        assert!(System::access(&resource_exists::<u32>).conflicts_with(&writes_count));
+    }
+
+    #[test]
+    fn mutable_condition_is_rejected() {
+        fn flip(mut flag: Resource<bool>) -> bool {
+            *flag = !*flag;
+            *flag
+        }
+
+        let mut world = World::new();
+        let Err(SystemError::MutableCondition { system, condition }) =
+            world.try_add_system(SystemSchedule::Update, count.run_if(flip))
+        else {
+            panic!("a condition writing a resource must be rejected");
+        };
+        assert!(system.ends_with("count"));
+        assert!(condition.ends_with("flip"));
+    }
+}
@@ -1,20 +1,20 @@
-use std::{cell::RefCell, rc::Rc};
+use std::sync::Arc;
 
 use super::{
-    access::AccessTracker, coroutine::CoroutineManager, entity_manager::EntityManager,
+    cell::AtomicRefCell, coroutine::CoroutineManager, entity_manager::EntityManager,
     event::EventManager, resources::ResourceManager, system::SystemManager, world::World,
 };
 
 /// A lightweight, cloneable handle to every manager owned by a [`World`], passed to systems
 /// instead of the `World` itself so a system's parameters (see [`SystemParam`](super::system::SystemParam))
-/// can each fetch just the manager they need.
+/// can each fetch just the manager they need — from whichever thread the system runs on.
+#[derive(Clone)]
 pub struct Coordinator {
-    pub entity_manager: Rc<RefCell<EntityManager>>,
-    pub system_manager: Rc<RefCell<SystemManager>>,
-    pub event_manager: Rc<RefCell<EventManager>>,
-    pub resources: Rc<RefCell<ResourceManager>>,
-    pub coroutine_manager: Rc<RefCell<CoroutineManager>>,
-    pub(crate) access_tracker: RefCell<AccessTracker>,
+    pub entity_manager: Arc<AtomicRefCell<EntityManager>>,
+    pub system_manager: Arc<AtomicRefCell<SystemManager>>,
+    pub event_manager: Arc<AtomicRefCell<EventManager>>,
+    pub resources: Arc<AtomicRefCell<ResourceManager>>,
+    pub coroutine_manager: Arc<AtomicRefCell<CoroutineManager>>,
 }
 
 impl Coordinator {
@@ -27,7 +27,6 @@ impl Coordinator {
             event_manager: world.event_manager.clone(),
             resources: world.resources.clone(),
             coroutine_manager: world.coroutine_manager.clone(),
-            access_tracker: RefCell::new(AccessTracker::default()),
         }
     }
 
@@ -1,4 +1,9 @@
-use super::{access::AccessKey, system::SystemParam, world::World};
+use super::{
+    access::{AccessKey, SystemAccess},
+    coordinator::Coordinator,
+    system::{SystemMeta, SystemParam},
+    world::World,
+};
 
 /// A pause duration yielded from a coroutine body via [`CoroutineState::Yielded`].
 #[derive(Clone, Copy, PartialEq, PartialOrd)]
@@ -21,7 +26,7 @@ pub enum CoroutineState {
 pub struct Coroutine {
     name: String,
     state: CoroutineState,
-    generator: Box<dyn FnMut(&mut World) -> CoroutineState + 'static>,
+    generator: Box<dyn FnMut(&mut World) -> CoroutineState + Send + Sync + 'static>,
     is_waiting: bool,
     amount_to_wait: f32,
 }
@@ -29,7 +34,10 @@ pub struct Coroutine {
 impl Coroutine {
     /// Creates a coroutine named `name`, running `generator` as its body. Panics later if
     /// added to a [`CoroutineManager`] that already has a coroutine with the same name.
-    pub fn new(name: &str, generator: impl FnMut(&mut World) -> CoroutineState + 'static) -> Self {
+    pub fn new(
+        name: &str,
+        generator: impl FnMut(&mut World) -> CoroutineState + Send + Sync + 'static,
+    ) -> Self {
         Self {
             name: name.to_owned(),
             state: CoroutineState::Running,
@@ -144,27 +152,29 @@ impl Default for CoroutineManager {
 }
 
 impl SystemParam for &CoroutineManager {
-    fn get_param(
-        coordinator: std::rc::Rc<std::cell::RefCell<super::coordinator::Coordinator>>,
-    ) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(std::any::TypeId::of::<CoroutineManager>()),
             false,
             "CoroutineManager",
         );
-        unsafe { &(*coordinator.borrow().get_coroutine_manager_mut()) }
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &(*coordinator.get_coroutine_manager_mut()) }
     }
 }
 
 impl SystemParam for &mut CoroutineManager {
-    fn get_param(
-        coordinator: std::rc::Rc<std::cell::RefCell<super::coordinator::Coordinator>>,
-    ) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(std::any::TypeId::of::<CoroutineManager>()),
             true,
             "CoroutineManager",
         );
-        unsafe { &mut (*coordinator.borrow().get_coroutine_manager_mut()) }
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &mut (*coordinator.get_coroutine_manager_mut()) }
     }
 }
@@ -1,24 +1,60 @@
-use super::{component::Component, world::World};
+use std::hash::{Hash, Hasher};
 
-/// Numeric identifier backing an [`Entity`], unique for as long as the entity is alive.
+use super::{change_detection::Mut, component::Component, world::World};
+
+/// Numeric identifier backing an [`Entity`], unique for as long as the entity is alive. Ids of
+/// removed entities are recycled, so an id alone doesn't identify an entity — pair it with
+/// [`Entity::generation`].
 pub type EntityId = u32;
 
+/// Where an entity's components currently live: the index of its archetype in
+/// [`EntityManager::archetypes`](super::entity_manager::EntityManager::archetypes), and its row
+/// within each of that archetype's component columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntityLocation {
+    pub archetype: usize,
+    pub row: usize,
+}
+
 /// A lightweight, `Copy` handle to a spawned entity. Holds no data itself — use
 /// [`Entity::get_component`]/[`Entity::get_component_mut`] (or a [`Query`](super::query::Query))
 /// against a [`World`] to read its components.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Handles compare and hash by [`Entity::id`] and [`Entity::generation`] only, so two handles to
+/// the same entity stay equal after it moves archetype.
+#[derive(Debug, Clone, Copy)]
 pub struct Entity {
     pub id: EntityId,
+    /// How many times `id` had already been recycled when this entity was spawned. A handle whose
+    /// generation doesn't match the id's current one refers to an entity that was removed, and
+    /// is rejected with [`QueryError::StaleEntity`](super::error::QueryError::StaleEntity).
+    pub generation: u32,
     /// Index into [`EntityManager::archetypes`](super::entity_manager::EntityManager::archetypes)
     /// of the archetype currently holding this entity's components. Changes whenever the
     /// entity's component set changes.
     pub entity_location: usize,
 }
 
+impl PartialEq for Entity {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.generation == other.generation
+    }
+}
+
+impl Eq for Entity {}
+
+impl Hash for Entity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.generation.hash(state);
+    }
+}
+
 impl Entity {
-    pub(crate) fn new(id: EntityId, entity_location: usize) -> Self {
+    pub(crate) fn new(id: EntityId, generation: u32, entity_location: usize) -> Self {
         Entity {
             id,
+            generation,
             entity_location,
         }
     }
@@ -32,11 +68,13 @@ impl Entity {
         }
     }
 
-    /// Mutable counterpart to [`Entity::get_component`].
-    pub fn get_component_mut<T: 'static + Component>(&mut self, world: &World) -> Option<&mut T> {
-        match world.entity_manager.borrow().get_component_mut::<T>(*self) {
-            Ok(component) => Some(unsafe { &mut *component }),
-            Err(_) => None,
-        }
+    /// Mutable counterpart to [`Entity::get_component`]. Writing through the returned [`Mut`]
+    /// marks the component as changed.
+    pub fn get_component_mut<'w, T: 'static + Component>(
+        &mut self,
+        world: &'w mut World,
+    ) -> Option<Mut<'w, T>> {
+        let entity_manager = unsafe { &mut *world.entity_manager.as_ptr() };
+        entity_manager.get_component_mut::<T>(*self).ok()
     }
 }
@@ -1,54 +1,90 @@
-use std::{
-    any::TypeId,
-    cell::{RefCell, UnsafeCell},
-    collections::HashMap,
-};
+use std::{any::TypeId, collections::HashMap, sync::Mutex};
 
-use crate::core::query::QuerySignature;
+use crate::core::query::QueryCache;
 
 use super::{
-    access::AccessKey,
+    access::{AccessKey, SystemAccess},
     archetype::{Archetype, MovedEntity},
-    component::{BundleComponent, Component},
-    entity::Entity,
+    change_detection::{self, Mut},
+    component::{BundleComponent, Column, Component, ComponentColumn},
+    coordinator::Coordinator,
+    entity::{Entity, EntityId, EntityLocation},
     error::QueryError,
-    system::SystemParam,
+    system::{SystemMeta, SystemParam},
 };
 
+/// Bookkeeping for one [`EntityId`], stored in [`EntityManager`]'s id-indexed location table.
+#[derive(Clone, Copy)]
+struct EntityMeta {
+    /// Current generation of the id. Bumped when the entity holding it is removed, so older
+    /// handles to it are recognised as stale.
+    generation: u32,
+    /// Where the entity's components live, or `None` while the id is free.
+    location: Option<EntityLocation>,
+    /// Index of the entity in [`EntityManager::entities`].
+    dense_index: usize,
+}
+
 /// Owns every entity and its components, grouped into [`Archetype`]s by component-type
 /// signature. Most callers interact with it indirectly through [`World`](super::world::World)
 /// rather than directly.
 pub struct EntityManager {
     pub entities: Vec<Entity>,
     pub archetypes: Vec<Archetype>,
-    next_entity_id: u32, // L6: Monotomic Incrementing Counter
+    /// Location table indexed by [`EntityId`], one entry for every id handed out so far, so an
+    /// [`Entity`] resolves to its archetype and row in constant time.
+    metas: Vec<EntityMeta>,
+    /// Ids of removed entities, reused by the next spawns before any fresh id is allocated.
+    free_ids: Vec<EntityId>,
+    /// Index of every archetype by its sorted component-type set, so spawns and migrations find
+    /// their destination without scanning [`EntityManager::archetypes`].
+    archetype_lookup: HashMap<Vec<TypeId>, usize>,
     pub(crate) archetype_version: u64,
-    pub(crate) query_cache: RefCell<HashMap<QuerySignature, (u64, Vec<usize>)>>,
+    /// Locked briefly by every query construction, which may happen on several worker threads
+    /// at once.
+    pub(crate) query_cache: Mutex<QueryCache>,
+    /// The current change tick: components added or written now are stamped with it.
+    /// Advanced after every system run (see
+    /// [`SystemTicks`](super::change_detection::SystemTicks)).
+    pub(crate) change_tick: u32,
+    /// Change tick at the start of the previous frame; removals older than it are dropped.
+    previous_frame_tick: u32,
+    /// Change tick at the last [`EntityManager::clamp_change_ticks`].
+    pub(crate) last_check_tick: u32,
+    /// Recently removed components, per type, with the change tick they were removed at. Read by
+    /// [`RemovedComponents`](super::change_detection::RemovedComponents).
+    pub(crate) removed_components: HashMap<TypeId, Vec<(Entity, u32)>>,
 }
 
+/// Reaches every component, so it counts as reading all of them: it conflicts with any
+/// [`Query`](super::query::Query) fetching a component mutably, as well as with
+/// `&mut EntityManager`.
 impl SystemParam for &EntityManager {
-    fn get_param(
-        coordinator: std::rc::Rc<std::cell::RefCell<super::coordinator::Coordinator>>,
-    ) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(TypeId::of::<EntityManager>()),
             false,
             "EntityManager",
         );
-        unsafe { &*coordinator.borrow().get_entity_manager_mut() }
+        access.add_component_reader();
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &*coordinator.get_entity_manager_mut() }
     }
 }
 
 impl SystemParam for &mut EntityManager {
-    fn get_param(
-        coordinator: std::rc::Rc<std::cell::RefCell<super::coordinator::Coordinator>>,
-    ) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(TypeId::of::<EntityManager>()),
             true,
             "EntityManager",
         );
-        unsafe { &mut *coordinator.borrow().get_entity_manager_mut() }
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &mut *coordinator.get_entity_manager_mut() }
     }
 }
 
@@ -58,210 +94,398 @@ impl EntityManager {
         EntityManager {
             entities: Vec::new(),
             archetypes: Vec::new(),
-            next_entity_id: 0,
+            metas: Vec::new(),
+            free_ids: Vec::new(),
+            archetype_lookup: HashMap::new(),
             archetype_version: 0,
-            query_cache: RefCell::new(HashMap::new()),
+            query_cache: Mutex::new(HashMap::new()),
+            change_tick: 1,
+            previous_frame_tick: 1,
+            last_check_tick: 1,
+            removed_components: HashMap::new(),
+        }
+    }
+
+    /// Marks the start of a new frame, forgetting removals recorded before the previous one
+    /// started. Removals thus stay visible for two frames: long enough for every system to see
+    /// each of them once, whether it runs before or after the removing system.
+    pub(crate) fn begin_frame(&mut self) {
+        let change_tick = self.change_tick;
+        let max_age = change_tick.wrapping_sub(self.previous_frame_tick);
+        for removed in self.removed_components.values_mut() {
+            removed.retain(|(_, tick)| change_tick.wrapping_sub(*tick) <= max_age);
         }
+        self.previous_frame_tick = change_tick;
+    }
+
+    /// Clamps every stored change tick (of components and removals) to at most
+    /// [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before the current
+    /// one, so none of them looks new again once the change tick wraps around.
+    pub(crate) fn clamp_change_ticks(&mut self) {
+        let change_tick = self.change_tick;
+        for archetype in &mut self.archetypes {
+            for column in archetype.components.values_mut() {
+                column.clamp_ticks(change_tick);
+            }
+        }
+        for removed in self.removed_components.values_mut() {
+            for (_, tick) in removed.iter_mut() {
+                change_detection::clamp_tick(tick, change_tick);
+            }
+        }
+        self.last_check_tick = change_tick;
     }
 
     /// Spawns a new entity with the given bundle of components, placing it in the matching
-    /// archetype (creating one if none matches yet), and returns its [`Entity`] id.
+    /// archetype (creating one if none matches yet), and returns its [`Entity`] id. Reuses the id
+    /// of a previously removed entity when one is available.
     pub fn create_entity(&mut self, components: impl BundleComponent) -> Entity {
         let mut types_ids = components.get_types_id();
-        let mut entity = Entity::new(self.next_entity_id, 0);
-        self.next_entity_id += 1;
+        let mut entity = self.allocate_entity();
 
         types_ids.sort();
 
-        let archetype_index_opt = self.archetypes.iter().position(|archetype| {
-            let mut arch_types_ids = archetype.components.keys().copied().collect::<Vec<_>>();
-            arch_types_ids.sort();
-            arch_types_ids.iter().eq(types_ids.iter())
-        });
+        let archetype_index_opt = self.archetype_lookup.get(&types_ids).copied();
 
-        match archetype_index_opt {
+        let archetype_index = archetype_index_opt.unwrap_or(self.archetypes.len());
+        entity.entity_location = archetype_index;
+        let row = match archetype_index_opt {
             Some(archetype_index) => {
-                self.archetypes[archetype_index].add_entity(entity.id, components);
-                entity.entity_location = archetype_index;
+                self.archetypes[archetype_index].add_entity(entity, components, self.change_tick)
             }
             None => {
-                let archetype = Archetype::new(entity.id, components);
+                let archetype = Archetype::new(entity, components, self.change_tick);
                 self.archetypes.push(archetype);
+                self.archetype_lookup.insert(types_ids, archetype_index);
                 self.archetype_version += 1;
-                entity.entity_location = self.archetypes.len() - 1;
+                0
             }
-        }
+        };
 
+        let meta = &mut self.metas[entity.id as usize];
+        meta.location = Some(EntityLocation {
+            archetype: archetype_index,
+            row,
+        });
+        meta.dense_index = self.entities.len();
         self.entities.push(entity);
         entity
     }
 
     /// Removes component `T` from `entity`, migrating it to the matching archetype (or
-    /// removing the entity entirely if it has no components left). No-op if `entity` doesn't
-    /// exist.
-    pub fn remove_component<T: 'static + Component>(&mut self, entity: Entity) {
-        let entity_id = entity.id;
-        let location = match self.entities.iter().find(|e| e.id == entity_id) {
-            Some(e) => e.entity_location,
-            None => return,
-        };
+    /// removing the entity entirely if it has no components left). No-op if `entity` doesn't have
+    /// a `T`; errors if `entity` doesn't exist or is stale.
+    pub fn remove_component<T: 'static + Component>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<(), QueryError> {
+        let location = self.locate(entity)?;
+        let source = location.archetype;
 
         let type_id = std::any::TypeId::of::<T>();
-        let mut entity_with_components = self.archetypes[location]
-            .migrate_entity_to_other_archetype(entity_id)
+        if !self.archetypes[source].has_type(type_id) {
+            return Ok(());
+        }
+        let target = self.neighbour_archetype(source, type_id, false);
+
+        let mut entity_with_components = self.archetypes[source]
+            .migrate_entity_to_other_archetype(location.row)
             .unwrap();
+        self.patch_swapped_row(location);
         entity_with_components.1.remove(&type_id);
-
-        let archetype_empty = self.archetypes[location].is_empty();
-        if archetype_empty {
-            self.remove_archetype(location);
-        }
+        self.record_removal(type_id, entity);
 
         if entity_with_components.1.is_empty() {
-            if let Some(pos) = self.entities.iter().position(|e| e.id == entity_id) {
-                self.entities.remove(pos);
-            }
+            self.free_entity(entity.id);
         } else {
-            // Recria entity com location atualizado após possível remove_archetype
-            let updated = match self.entities.iter().find(|e| e.id == entity_id) {
-                Some(e) => *e,
-                None => return,
-            };
-            self.move_entity_to_other_archetype(updated, entity_with_components.1);
+            let target =
+                self.move_entity_to_other_archetype(entity.id, target, entity_with_components.1);
+            self.link_archetypes(target, source, type_id);
         }
+        Ok(())
     }
 
     /// Adds (or replaces) component `T` on `entity`, migrating it to the matching archetype.
-    /// No-op if `entity` doesn't exist.
+    /// Errors if `entity` doesn't exist or is stale.
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
         entity: Entity,
         component: T,
-    ) {
-        let entity_id = entity.id;
-        let location = match self.entities.iter().find(|e| e.id == entity_id) {
-            Some(e) => e.entity_location,
-            None => return,
-        };
+    ) -> Result<(), QueryError> {
+        let location = self.locate(entity)?;
+        let source = location.archetype;
 
         let type_id = std::any::TypeId::of::<T>();
-        let mut entity_with_components = self.archetypes[location]
-            .migrate_entity_to_other_archetype(entity_id)
+        let change_tick = self.change_tick;
+        if let Some(mut existing) =
+            self.archetypes[source].get_component_mut::<T>(location.row, change_tick)
+        {
+            // Same component set: replace the value in place, no migration needed.
+            *existing = component;
+            return Ok(());
+        }
+        let target = self.neighbour_archetype(source, type_id, true);
+
+        let mut entity_with_components = self.archetypes[source]
+            .migrate_entity_to_other_archetype(location.row)
             .unwrap();
+        self.patch_swapped_row(location);
         entity_with_components.1.insert(
             type_id,
-            Box::new(UnsafeCell::new(vec![component]))
-                as Box<dyn super::component::ComponentColumn>,
+            Box::new(Column::new(component, self.change_tick)) as Box<dyn ComponentColumn>,
         );
 
-        let archetype_empty = self.archetypes[location].is_empty();
-        if archetype_empty {
-            self.remove_archetype(location);
+        let target =
+            self.move_entity_to_other_archetype(entity.id, target, entity_with_components.1);
+        self.link_archetypes(source, target, type_id);
+        Ok(())
+    }
+
+    /// Removes `entity` and all of its components, freeing its id for reuse. Errors if `entity`
+    /// doesn't exist or is stale.
+    pub fn remove_entity(&mut self, entity: Entity) -> Result<(), QueryError> {
+        let location = self.locate(entity)?;
+
+        for type_id in self.archetypes[location.archetype].sorted_type_ids() {
+            self.record_removal(type_id, entity);
         }
+        self.archetypes[location.archetype]
+            .remove_entity(location.row)
+            .unwrap();
+        self.patch_swapped_row(location);
 
-        let updated = match self.entities.iter().find(|e| e.id == entity_id) {
-            Some(e) => *e,
-            None => return,
-        };
-        self.move_entity_to_other_archetype(updated, entity_with_components.1);
+        self.free_entity(entity.id);
+        Ok(())
     }
 
-    /// Removes `entity` and all of its components. No-op if `entity` doesn't exist.
-    pub fn remove_entity(&mut self, entity: Entity) {
-        let entity_id = entity.id;
-        let location = match self.entities.iter().find(|e| e.id == entity_id) {
-            Some(e) => e.entity_location,
-            None => return,
-        };
+    /// Logs that `entity` lost its component `type_id` at the current change tick.
+    fn record_removal(&mut self, type_id: TypeId, entity: Entity) {
+        self.removed_components
+            .entry(type_id)
+            .or_default()
+            .push((entity, self.change_tick));
+    }
 
-        self.archetypes[location].remove_entity(entity_id).unwrap();
+    /// Hands out the id for a new entity: a recycled one (keeping the generation it was bumped
+    /// to on removal) if available, otherwise a fresh one. The caller must fill in its location.
+    fn allocate_entity(&mut self) -> Entity {
+        match self.free_ids.pop() {
+            Some(id) => Entity::new(id, self.metas[id as usize].generation, 0),
+            None => {
+                let id = self.metas.len() as EntityId;
+                self.metas.push(EntityMeta {
+                    generation: 0,
+                    location: None,
+                    dense_index: 0,
+                });
+                Entity::new(id, 0, 0)
+            }
+        }
+    }
 
-        if self.archetypes[location].is_empty() {
-            self.remove_archetype(location);
+    /// Retires `id` after its entity left its archetype, invalidating every outstanding handle to
+    /// it.
+    fn free_entity(&mut self, id: EntityId) {
+        let meta = &mut self.metas[id as usize];
+        meta.generation = meta.generation.wrapping_add(1);
+        meta.location = None;
+        let dense_index = meta.dense_index;
+
+        self.entities.swap_remove(dense_index);
+        if let Some(moved) = self.entities.get(dense_index) {
+            self.metas[moved.id as usize].dense_index = dense_index;
         }
+        self.free_ids.push(id);
+    }
 
-        // C4/C5: busca posição pelo id, não pelo entity_location
-        if let Some(pos) = self.entities.iter().position(|e| e.id == entity_id) {
-            self.entities.remove(pos);
+    /// Returns where `entity`'s components live, or a [`QueryError`] if the id was never handed
+    /// out, or the handle's generation shows its entity has since been removed.
+    pub(crate) fn locate(&self, entity: Entity) -> Result<EntityLocation, QueryError> {
+        let meta = self
+            .metas
+            .get(entity.id as usize)
+            .ok_or(QueryError::EntityNotFound(entity.id))?;
+        if meta.generation != entity.generation {
+            return Err(QueryError::StaleEntity(entity.id));
         }
+        meta.location.ok_or(QueryError::EntityNotFound(entity.id))
     }
 
-    // C7: ordena as keys antes de comparar — HashMap não tem ordem definida
-    fn move_entity_to_other_archetype(&mut self, entity: Entity, components: MovedEntity) {
-        let mut types_ids = components.keys().copied().collect::<Vec<_>>();
-        types_ids.sort();
+    /// Records that entity `id` now lives at `location`, keeping the location table, the
+    /// [`EntityManager::entities`] list and the entity's own `Entity` component in sync.
+    fn set_location(&mut self, id: EntityId, location: EntityLocation) {
+        let meta = &mut self.metas[id as usize];
+        meta.location = Some(location);
+        self.entities[meta.dense_index].entity_location = location.archetype;
+        // Written through the column directly: relocating isn't a change to report.
+        if let Some(column) = self.archetypes[location.archetype].column::<Entity>()
+            && let Some(stored) = unsafe { (&mut *column.data.get()).get_mut(location.row) }
+        {
+            stored.entity_location = location.archetype;
+        }
+    }
 
-        let archetype_index = self.archetypes.iter().position(|archetype| {
-            let mut arch_keys = archetype.components.keys().copied().collect::<Vec<_>>();
-            arch_keys.sort();
-            arch_keys == types_ids
-        });
+    /// After a `swap_remove` at `location.row`, points the entity that was swapped into that row
+    /// (the archetype's previous last entity, if any) at its new row.
+    fn patch_swapped_row(&mut self, location: EntityLocation) {
+        if let Some(&moved) = self.archetypes[location.archetype]
+            .entities
+            .get(location.row)
+        {
+            self.metas[moved as usize].location = Some(location);
+        }
+    }
 
-        if let Some(archetype_index) = archetype_index {
-            self.archetypes[archetype_index].add_entity_migrated(entity.id, components);
-            if let Some(e) = self.entities.iter_mut().find(|e| e.id == entity.id) {
-                e.entity_location = archetype_index;
-            }
+    /// Returns the archetype reached from `source` by adding (`adding`) or removing component
+    /// `type_id`: straight from `source`'s cached edge when there is one, otherwise by looking up
+    /// the resulting type set. `None` if no such archetype exists yet.
+    fn neighbour_archetype(&self, source: usize, type_id: TypeId, adding: bool) -> Option<usize> {
+        let archetype = &self.archetypes[source];
+        let edges = if adding {
+            &archetype.add_edges
         } else {
-            let archetype = Archetype::new_from_migration(entity.id, components);
-            self.archetypes.push(archetype);
-            self.archetype_version += 1;
-            let new_idx = self.archetypes.len() - 1;
-            if let Some(e) = self.entities.iter_mut().find(|e| e.id == entity.id) {
-                e.entity_location = new_idx;
-            }
+            &archetype.remove_edges
+        };
+        if let Some(&target) = edges.get(&type_id) {
+            return Some(target);
         }
+
+        let mut types_ids = archetype.sorted_type_ids();
+        if adding {
+            types_ids.push(type_id);
+            types_ids.sort();
+        } else {
+            types_ids.retain(|t| *t != type_id);
+        }
+        self.archetype_lookup.get(&types_ids).copied()
+    }
+
+    /// Caches the graph edges between archetype `without` and archetype `with`, whose type sets
+    /// differ only by `type_id`, in both directions.
+    fn link_archetypes(&mut self, without: usize, with: usize, type_id: TypeId) {
+        self.archetypes[without].add_edges.insert(type_id, with);
+        self.archetypes[with].remove_edges.insert(type_id, without);
+    }
+
+    /// Inserts a migrating entity's `components` into `target` (as found by
+    /// [`EntityManager::neighbour_archetype`]), or into a new archetype if `target` is `None`.
+    /// Returns the index of the archetype the entity ended up in.
+    fn move_entity_to_other_archetype(
+        &mut self,
+        entity_id: EntityId,
+        target: Option<usize>,
+        components: MovedEntity,
+    ) -> usize {
+        let location = match target {
+            Some(archetype_index) => EntityLocation {
+                archetype: archetype_index,
+                row: self.archetypes[archetype_index].add_entity_migrated(entity_id, components),
+            },
+            None => {
+                // C7: ordena as keys — HashMap não tem ordem definida
+                let mut types_ids = components.keys().copied().collect::<Vec<_>>();
+                types_ids.sort();
+
+                let archetype = Archetype::new_from_migration(entity_id, components);
+                self.archetypes.push(archetype);
+                let archetype_index = self.archetypes.len() - 1;
+                self.archetype_lookup.insert(types_ids, archetype_index);
+                self.archetype_version += 1;
+                EntityLocation {
+                    archetype: archetype_index,
+                    row: 0,
+                }
+            }
+        };
+        self.set_location(entity_id, location);
+        location.archetype
     }
 
     /// Returns a raw pointer to `entity`'s component `T`, or a [`QueryError`] if either the
-    /// entity or the component doesn't exist.
+    /// entity or the component doesn't exist, or if `entity` is a stale handle.
     pub fn get_component<T: 'static + Component>(
         &self,
         entity: Entity,
     ) -> Result<*const T, QueryError> {
-        let entity_opt = self.entities.iter().find(|ent| ent.id == entity.id);
-        if let Some(entity) = entity_opt {
-            let archetype = &self.archetypes[entity.entity_location];
-            let component = archetype.get_component::<T>(entity.id);
-            match component {
-                Some(component) => Ok(component),
-                None => Err(QueryError::ComponentNotFound(format!(
-                    "Component Type {:?}",
-                    std::any::type_name::<T>()
-                ))),
-            }
-        } else {
-            Err(QueryError::EntityNotFound(entity.id))
+        let location = self.locate(entity)?;
+        let archetype = &self.archetypes[location.archetype];
+        match archetype.get_component::<T>(location.row) {
+            Some(component) => Ok(component),
+            None => Err(QueryError::ComponentNotFound(format!(
+                "Component Type {:?}",
+                std::any::type_name::<T>()
+            ))),
         }
     }
 
-    /// Mutable counterpart to [`EntityManager::get_component`].
+    /// Mutable counterpart to [`EntityManager::get_component`]. Writing through the returned
+    /// [`Mut`] marks the component as changed.
     pub fn get_component_mut<T: 'static + Component>(
-        &self,
+        &mut self,
         entity: Entity,
-    ) -> Result<*mut T, QueryError> {
-        let entity_opt = self.entities.iter().find(|ent| ent.id == entity.id);
-        if let Some(entity) = entity_opt {
-            let archetype = &self.archetypes[entity.entity_location];
-            let component = archetype.get_component_mut::<T>(entity.id);
-            match component {
-                Some(component) => Ok(component),
-                None => Err(QueryError::ComponentNotFound(format!(
-                    "Component Type {:?}",
-                    std::any::type_name::<T>()
-                ))),
-            }
-        } else {
-            Err(QueryError::EntityNotFound(entity.id))
+    ) -> Result<Mut<'_, T>, QueryError> {
+        let location = self.locate(entity)?;
+        let change_tick = self.change_tick;
+        let archetype = &mut self.archetypes[location.archetype];
+        match archetype.get_component_mut::<T>(location.row, change_tick) {
+            Some(component) => Ok(component),
+            None => Err(QueryError::ComponentNotFound(format!(
+                "Component Type {:?}",
+                std::any::type_name::<T>()
+            ))),
         }
     }
 
-    fn remove_archetype(&mut self, idx: usize) {
-        self.archetypes.remove(idx);
+    /// Drops every archetype that no longer holds any entities, reclaiming their storage.
+    ///
+    /// Archetypes are otherwise kept around once they empty out, so archetype indices — and with
+    /// them [`Entity::entity_location`], cached query matches and archetype-graph edges — stay
+    /// valid for the lifetime of the manager. Compacting renumbers the remaining archetypes:
+    /// every live entity's location is updated and the query cache is invalidated, but `Entity`
+    /// copies taken before the call keep their old `entity_location`.
+    pub fn compact(&mut self) {
+        if !self.archetypes.iter().any(Archetype::is_empty) {
+            return;
+        }
+
+        let mut remap = Vec::with_capacity(self.archetypes.len());
+        let mut next_index = 0;
+        for archetype in &self.archetypes {
+            if archetype.is_empty() {
+                remap.push(None);
+            } else {
+                remap.push(Some(next_index));
+                next_index += 1;
+            }
+        }
+        let remap_index = |index: &mut usize| match remap[*index] {
+            Some(new_index) => {
+                *index = new_index;
+                true
+            }
+            None => false,
+        };
+
+        self.archetypes.retain(|archetype| !archetype.is_empty());
         self.archetype_version += 1;
-        for entity in self.entities.iter_mut() {
-            if entity.entity_location > idx {
-                entity.entity_location -= 1;
+
+        self.archetype_lookup.retain(|_, index| remap_index(index));
+        for archetype in self.archetypes.iter_mut() {
+            archetype.add_edges.retain(|_, index| remap_index(index));
+            archetype.remove_edges.retain(|_, index| remap_index(index));
+        }
+
+        for index in 0..self.entities.len() {
+            let entity_id = self.entities[index].id;
+            if let Some(location) = self.metas[entity_id as usize].location {
+                self.set_location(
+                    entity_id,
+                    EntityLocation {
+                        archetype: remap[location.archetype]
+                            .expect("live entity in an empty archetype"),
+                        row: location.row,
+                    },
+                );
             }
         }
     }
@@ -276,6 +500,7 @@ impl Default for EntityManager {
 #[cfg(test)]
 pub mod storage_regression_test {
     use super::*;
+    use std::{collections::HashSet, sync::Arc};
 
     #[derive(PartialEq, Eq, Debug)]
     struct A(i32);
@@ -290,7 +515,7 @@ pub mod storage_regression_test {
     fn migration_preserve_other_components_on_remove() {
         let mut em = EntityManager::default();
         let entity = em.create_entity((A(1), B(2), C(3)));
-        em.remove_component::<B>(entity);
+        em.remove_component::<B>(entity).unwrap();
 
         let a = em.get_component::<A>(entity).unwrap();
         let c = em.get_component::<C>(entity).unwrap();
@@ -304,7 +529,7 @@ pub mod storage_regression_test {
         let mut em = EntityManager::new();
         let entity = em.create_entity((A(1),));
 
-        em.add_component_to_entity(entity, B(2));
+        em.add_component_to_entity(entity, B(2)).unwrap();
 
         let a = em.get_component::<A>(entity).unwrap();
         let b = em.get_component::<B>(entity).unwrap();
@@ -319,7 +544,7 @@ pub mod storage_regression_test {
         let e2 = em.create_entity((A(2),));
         let e3 = em.create_entity((A(3),));
 
-        em.remove_entity(e2);
+        em.remove_entity(e2).unwrap();
 
         let a1 = em.get_component::<A>(e1).unwrap();
         let a3 = em.get_component::<A>(e3).unwrap();
@@ -335,7 +560,7 @@ pub mod storage_regression_test {
         let e2 = em.create_entity((A(20), B(200)));
         let e3 = em.create_entity((A(30), B(300)));
 
-        em.remove_entity(e2);
+        em.remove_entity(e2).unwrap();
 
         assert_eq!(unsafe { &*em.get_component::<A>(e1).unwrap() }, &A(10));
         assert_eq!(unsafe { &*em.get_component::<B>(e1).unwrap() }, &B(100));
@@ -343,6 +568,129 @@ pub mod storage_regression_test {
         assert_eq!(unsafe { &*em.get_component::<B>(e3).unwrap() }, &B(300));
     }
 
+    #[test]
+    fn removed_entity_id_is_reused_with_a_new_generation() {
+        let mut em = EntityManager::new();
+        let e1 = em.create_entity((A(1),));
+        em.remove_entity(e1).unwrap();
+
+        let e2 = em.create_entity((A(2),));
+        assert_eq!(e2.id, e1.id);
+        assert_ne!(e2.generation, e1.generation);
+        assert_eq!(unsafe { &*em.get_component::<A>(e2).unwrap() }, &A(2));
+    }
+
+    #[test]
+    fn stale_handle_is_rejected_and_does_not_touch_the_respawned_entity() {
+        let mut em = EntityManager::new();
+        let stale = em.create_entity((A(1),));
+        em.remove_entity(stale).unwrap();
+        let respawned = em.create_entity((A(2),));
+
+        assert!(matches!(
+            em.get_component::<A>(stale),
+            Err(QueryError::StaleEntity(id)) if id == stale.id
+        ));
+        assert!(matches!(
+            em.add_component_to_entity(stale, B(1)),
+            Err(QueryError::StaleEntity(_))
+        ));
+        assert!(matches!(
+            em.remove_entity(stale),
+            Err(QueryError::StaleEntity(_))
+        ));
+
+        assert_eq!(
+            unsafe { &*em.get_component::<A>(respawned).unwrap() },
+            &A(2)
+        );
+        assert!(em.get_component::<B>(respawned).is_err());
+    }
+
+    #[test]
+    fn location_table_follows_entities_swapped_by_a_migration() {
+        let mut em = EntityManager::new();
+        let e1 = em.create_entity((A(1),));
+        let e2 = em.create_entity((A(2),));
+        let e3 = em.create_entity((A(3),));
+
+        // Moving e1 out of row 0 swaps e3 into its place.
+        em.add_component_to_entity(e1, B(10)).unwrap();
+        em.remove_component::<A>(e2).unwrap();
+
+        assert_eq!(unsafe { &*em.get_component::<A>(e1).unwrap() }, &A(1));
+        assert_eq!(unsafe { &*em.get_component::<B>(e1).unwrap() }, &B(10));
+        assert_eq!(unsafe { &*em.get_component::<A>(e3).unwrap() }, &A(3));
+        assert!(em.get_component::<A>(e2).is_err());
+
+        let stored = unsafe { &*em.get_component::<Entity>(e1).unwrap() };
+        assert_eq!(stored.id, e1.id);
+        assert_eq!(em.archetypes[stored.entity_location].entities, vec![e1.id]);
+    }
+
+    #[test]
+    fn handles_stay_equal_after_a_migration_and_compact() {
+        let mut em = EntityManager::new();
+        let gone = em.create_entity((C(0),));
+        let e1 = em.create_entity((A(1),));
+        let mut seen = HashSet::new();
+        seen.insert(e1);
+
+        em.add_component_to_entity(e1, B(10)).unwrap();
+        em.remove_entity(gone).unwrap();
+        em.compact();
+
+        let stored = unsafe { *em.get_component::<Entity>(e1).unwrap() };
+        assert_ne!(stored.entity_location, e1.entity_location);
+        assert_eq!(stored, e1);
+        assert!(seen.contains(&stored));
+    }
+
+    #[test]
+    fn toggling_a_marker_component_caches_graph_edges_both_ways() {
+        let mut em = EntityManager::new();
+        let e1 = em.create_entity((A(1),));
+        let e2 = em.create_entity((A(2),));
+        let source = em.locate(e1).unwrap().archetype;
+
+        em.add_component_to_entity(e1, B(0)).unwrap();
+        let target = em.locate(e1).unwrap().archetype;
+        assert_eq!(
+            em.archetypes[source].add_edges.get(&TypeId::of::<B>()),
+            Some(&target)
+        );
+        assert_eq!(
+            em.archetypes[target].remove_edges.get(&TypeId::of::<B>()),
+            Some(&source)
+        );
+
+        em.add_component_to_entity(e2, B(0)).unwrap();
+        assert_eq!(
+            em.locate(e2).unwrap().archetype,
+            em.locate(e1).unwrap().archetype
+        );
+
+        em.remove_component::<B>(e1).unwrap();
+        assert_eq!(unsafe { &*em.get_component::<A>(e1).unwrap() }, &A(1));
+        assert_eq!(unsafe { &*em.get_component::<A>(e2).unwrap() }, &A(2));
+        assert!(em.get_component::<B>(e1).is_err());
+        assert!(em.get_component::<B>(e2).is_ok());
+    }
+
+    #[test]
+    fn adding_an_existing_component_replaces_it_without_migrating() {
+        let mut em = EntityManager::new();
+        let entity = em.create_entity((A(1), B(1)));
+        let before = em.locate(entity).unwrap();
+        let version = em.archetype_version;
+
+        em.add_component_to_entity(entity, B(2)).unwrap();
+
+        assert_eq!(em.locate(entity).unwrap(), before);
+        assert_eq!(em.archetype_version, version);
+        assert_eq!(unsafe { &*em.get_component::<B>(entity).unwrap() }, &B(2));
+    }
+
     #[test]
     fn archetype_version_bumps_only_on_new_archetype_shape() {
         let mut em = EntityManager::new();
@@ -362,28 +710,83 @@ pub mod storage_regression_test {
     }
 
     #[test]
-    fn archetype_version_bumps_when_an_archetype_is_removed() {
+    fn archetype_version_bumps_when_compact_prunes_an_archetype() {
         let mut em = EntityManager::new();
         let e1 = em.create_entity((A(1), B(1)));
         let _e2 = em.create_entity((A(2),)); // second, different shape
         let v_before = em.archetype_version;
 
-        // Removing B leaves e1's old (A, B) archetype empty, which gets pruned.
-        em.remove_component::<B>(e1);
+        // Removing B leaves e1's old (A, B) archetype empty, which is kept until compacted.
+        em.remove_component::<B>(e1).unwrap();
+        assert_eq!(em.archetype_version, v_before);
+        assert_eq!(em.archetypes.len(), 2);
+
+        em.compact();
 
         assert!(em.archetype_version > v_before);
+        assert_eq!(em.archetypes.len(), 1);
+    }
+
+    #[test]
+    fn emptied_archetypes_keep_their_index_and_edges() {
+        let mut em = EntityManager::new();
+        let marker_free = em.create_entity((A(1),));
+        let other = em.create_entity((C(0),));
+        let source = em.locate(marker_free).unwrap().archetype;
+        let other_location = em.locate(other).unwrap();
+
+        em.add_component_to_entity(marker_free, B(0)).unwrap();
+        let target = em.locate(marker_free).unwrap().archetype;
+        assert!(em.archetypes[source].is_empty());
+        assert_eq!(em.locate(other).unwrap(), other_location);
+
+        em.remove_component::<B>(marker_free).unwrap();
+        assert_eq!(em.locate(marker_free).unwrap().archetype, source);
+        assert!(em.archetypes[target].is_empty());
+        assert_eq!(
+            em.archetypes[source].add_edges.get(&TypeId::of::<B>()),
+            Some(&target)
+        );
+    }
+
+    #[test]
+    fn compact_keeps_surviving_entities_reachable() {
+        let mut em = EntityManager::new();
+        let gone = em.create_entity((A(1),));
+        let e1 = em.create_entity((A(2), B(2)));
+        let e2 = em.create_entity((C(3),));
+        em.remove_entity(gone).unwrap();
+
+        em.compact();
+
+        assert_eq!(em.archetypes.len(), 2);
+        assert_eq!(unsafe { &*em.get_component::<B>(e1).unwrap() }, &B(2));
+        assert_eq!(unsafe { &*em.get_component::<C>(e2).unwrap() }, &C(3));
+        let stored = unsafe { &*em.get_component::<Entity>(e2).unwrap() };
+        assert_eq!(em.archetypes[stored.entity_location].entities, vec![e2.id]);
+
+        // The pruned (A,) shape is rebuilt on demand.
+        let respawned = em.create_entity((A(4),));
+        assert_eq!(
+            unsafe { &*em.get_component::<A>(respawned).unwrap() },
+            &A(4)
+        );
     }
 
     #[test]
     fn query_cache_field_starts_empty_and_round_trips_an_entry() {
         let em = EntityManager::new();
-        assert!(em.query_cache.borrow().is_empty());
+        assert!(em.query_cache.lock().unwrap().is_empty());
 
         let sig = crate::core::query::QuerySignature::new(vec![], vec![], vec![]);
         em.query_cache
-            .borrow_mut()
-            .insert(sig.clone(), (0, vec![1, 2]));
+            .lock()
+            .unwrap()
+            .insert(sig.clone(), (0, Arc::from([1, 2])));
 
-        assert_eq!(em.query_cache.borrow().get(&sig), Some(&(0, vec![1, 2])));
+        assert_eq!(
+            em.query_cache.lock().unwrap().get(&sig),
+            Some(&(0, Arc::from([1, 2])))
+        );
     }
 }
@@ -15,8 +15,69 @@ pub enum ArchetypeError {
 pub enum QueryError {
     /// No entity with this id exists.
     EntityNotFound(EntityId),
+    /// The handle refers to an entity that has since been removed; its id may already belong to
+    /// a newer entity.
+    StaleEntity(EntityId),
     /// The entity exists but doesn't have a component of this type.
     ComponentNotFound(String),
+    /// The entity exists but isn't matched by the query: it lacks a fetched or `With` component,
+    /// has a `Without` one, or fails an [`Added`](super::query::Added)/
+    /// [`Changed`](super::query::Changed) filter.
+    QueryDoesNotMatch(EntityId),
+    /// The same entity was requested more than once from a query fetching components mutably,
+    /// which would hand out two mutable references to the same component.
+    DuplicateEntity(EntityId),
+}
+
+/// Errors returned by [`Query::get_single`](super::query::Query::get_single) and
+/// [`Query::get_single_mut`](super::query::Query::get_single_mut), naming the query's fetched
+/// types.
+#[derive(Debug, PartialEq, Eq)]
+pub enum QuerySingleError {
+    /// The query matched no entity.
+    NoEntities(&'static str),
+    /// The query matched more than one entity.
+    MultipleEntities(&'static str),
+}
+
+/// Errors returned when registering a system, e.g. via
+/// [`World::try_add_system`](super::world::World::try_add_system).
+#[derive(Debug, PartialEq, Eq)]
+pub enum SystemError {
+    /// Two parameters of the system access the same data, at least one of them mutably. Holds
+    /// the system's name and a description of the conflict.
+    ConflictingAccess {
+        system: &'static str,
+        conflict: String,
+    },
+    /// The system's ordering constraints form a cycle with those of already-registered
+    /// systems. Holds the names of the systems in the cycle, in the order they'd have to run.
+    OrderingCycle(Vec<&'static str>),
+    /// A run condition of the system accesses data mutably. Holds the names of the system and
+    /// of the condition.
+    MutableCondition {
+        system: &'static str,
+        condition: &'static str,
+    },
+    /// The system takes an [`In`](super::system::In) parameter but isn't piped into from
+    /// another system. Holds the system's name and the parameter's type.
+    UnpipedInput {
+        system: &'static str,
+        input: &'static str,
+    },
+    /// The system was added to [`SystemSchedule::StateTransition`], which only applies state
+    /// transitions and never runs systems added to it. Holds the system's name.
+    ///
+    /// [`SystemSchedule::StateTransition`]: super::system::SystemSchedule::StateTransition
+    StateTransitionSchedule(&'static str),
+}
+
+/// An error returned by a system, with the name of the system that returned it. Passed to the
+/// error handler set via [`World::set_error_handler`](super::world::World::set_error_handler).
+#[derive(Debug)]
+pub struct SystemFailure {
+    pub system: &'static str,
+    pub error: Box<dyn Error + Send + Sync>,
 }
 
 impl std::fmt::Display for ArchetypeError {
@@ -31,12 +92,93 @@ impl std::fmt::Display for QueryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             QueryError::EntityNotFound(id) => write!(f, "QueryError: entity {} not found", id),
+            QueryError::StaleEntity(id) => {
+                write!(f, "QueryError: entity {} is stale (it was removed)", id)
+            }
             QueryError::ComponentNotFound(name) => {
                 write!(f, "QueryError: component '{}' not found", name)
             }
+            QueryError::QueryDoesNotMatch(id) => {
+                write!(f, "QueryError: entity {} doesn't match the query", id)
+            }
+            QueryError::DuplicateEntity(id) => {
+                write!(f, "QueryError: entity {} was requested more than once", id)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for QuerySingleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuerySingleError::NoEntities(query) => {
+                write!(f, "QuerySingleError: Query<{}> matched no entities", query)
+            }
+            QuerySingleError::MultipleEntities(query) => {
+                write!(
+                    f,
+                    "QuerySingleError: Query<{}> matched more than one entity",
+                    query
+                )
+            }
         }
     }
 }
 
+impl std::fmt::Display for SystemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SystemError::ConflictingAccess { system, conflict } => {
+                write!(f, "SystemError: system '{}' rejected: {}", system, conflict)
+            }
+            SystemError::OrderingCycle(systems) => {
+                write!(
+                    f,
+                    "SystemError: ordering cycle between systems {} -> {}",
+                    systems.join(" -> "),
+                    systems[0]
+                )
+            }
+            SystemError::MutableCondition { system, condition } => {
+                write!(
+                    f,
+                    "SystemError: system '{}' rejected: run condition '{}' must be read-only",
+                    system, condition
+                )
+            }
+            SystemError::UnpipedInput { system, input } => {
+                write!(
+                    f,
+                    "SystemError: system '{}' rejected: In<{}> is only provided to a system \
+                     piped into from another one",
+                    system, input
+                )
+            }
+            SystemError::StateTransitionSchedule(system) => {
+                write!(
+                    f,
+                    "SystemError: system '{}' rejected: SystemSchedule::StateTransition only \
+                     applies state transitions; add it to OnEnter/OnExit/OnTransition instead",
+                    system
+                )
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SystemFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SystemFailure: system '{}' failed: {}", self.system, self.error)
+    }
+}
+
 impl Error for ArchetypeError {}
 impl Error for QueryError {}
+impl Error for QuerySingleError {}
+impl Error for SystemError {}
+
+impl Error for SystemFailure {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
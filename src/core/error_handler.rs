@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use super::{error::SystemFailure, world::World};
+
+/// Called with every error a system returns, on the thread running the schedule, once the
+/// system's batch has finished. Set via
+/// [`World::set_error_handler`](super::world::World::set_error_handler); [`panic_on_error`] by
+/// default.
+pub type ErrorHandler = Arc<dyn Fn(&World, SystemFailure) + Send + Sync>;
+
+/// Panics with the failure, like an `unwrap` inside the system would. The default handler.
+pub fn panic_on_error(_world: &World, failure: SystemFailure) {
+    panic!("{failure}");
+}
+
+/// Prints the failure to stderr and keeps going.
+pub fn log_error(_world: &World, failure: SystemFailure) {
+    eprintln!("{failure}");
+}
+
+/// Appends the failure to the [`SystemErrors`] resource, adding it if needed, so the
+/// application can inspect errors at its own pace.
+pub fn collect_errors(world: &World, failure: SystemFailure) {
+    let errors = world.get_resource::<SystemErrors>();
+    match errors {
+        Some(mut errors) => errors.0.push(failure),
+        None => world
+            .resources
+            .borrow_mut()
+            .add(SystemErrors(vec![failure])),
+    }
+}
+
+/// Errors collected by [`collect_errors`], oldest first.
+#[derive(Debug, Default)]
+pub struct SystemErrors(pub Vec<SystemFailure>);
+
+#[cfg(test)]
+mod error_handler_test {
+    use std::error::Error;
+
+    use super::{SystemErrors, collect_errors};
+    use crate::core::{
+        entity::Entity,
+        entity_manager::EntityManager,
+        error::QueryError,
+        query::Query,
+        resources::Resource,
+        system::{IntoSystemConfig, SystemSchedule},
+        world::World,
+    };
+
+    struct Health(i32);
+
+    struct Target(Entity);
+
+    fn damage_target(
+        mut q: Query<(&mut Health,)>,
+        target: Resource<Target>,
+    ) -> Result<(), QueryError> {
+        let mut health = q.get_mut(target.0)?;
+        health.0 -= 10;
+        Ok(())
+    }
+
+    fn shoot(_: &EntityManager) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("out of ammo".into())
+    }
+
+    #[test]
+    fn returned_errors_reach_the_handler_in_run_order() {
+        let mut world = World::new();
+        let target = world.create_entity_with_id((Health(100),));
+        world.remove_entity(target);
+        world
+            .add_resource(Target(target))
+            .set_error_handler(collect_errors)
+            .add_system(SystemSchedule::Update, shoot.after("damage"))
+            .add_system(SystemSchedule::Update, damage_target.label("damage"));
+
+        world.run_update();
+        let errors = world.get_resource::<SystemErrors>().unwrap();
+        assert_eq!(errors.0.len(), 2);
+        assert!(errors.0[0].system.ends_with("damage_target"));
+        assert!(errors.0[0].error.to_string().starts_with("QueryError"));
+        assert_eq!(errors.0[1].error.to_string(), "out of ammo");
+    }
+
+    #[test]
+    #[should_panic(expected = "failed: out of ammo")]
+    fn errors_panic_by_default() {
+        World::new()
+            .add_system(SystemSchedule::Update, shoot)
+            .run_update();
+    }
+}
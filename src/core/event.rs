@@ -1,16 +1,18 @@
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
     collections::HashMap,
-    rc::{Rc, Weak},
+    sync::Weak,
 };
 
 use super::{
-    access::AccessKey, as_any_trait::AsAny, coordinator::Coordinator, system::SystemParam,
+    access::{AccessKey, SystemAccess},
+    as_any_trait::AsAny,
+    coordinator::Coordinator,
+    system::{SystemMeta, SystemParam},
     world::World,
 };
 
-type EventFunction<T> = Box<dyn Fn(&World, T)>;
+type EventFunction<T> = Box<dyn Fn(&World, T) + Send + Sync>;
 
 /// A subscribed callback for events of type `T`, wrapping a `Fn(&World, T)` closure.
 ///
@@ -18,17 +20,17 @@ type EventFunction<T> = Box<dyn Fn(&World, T)>;
 /// rather than constructed directly.
 pub struct EventHandler<T> {
     pub func: EventFunction<T>,
-    _marker: std::marker::PhantomData<T>,
+    _marker: std::marker::PhantomData<fn(T)>,
 }
 
 /// Object-safe marker implemented by every [`EventHandler<T>`], letting [`EventManager`] store
 /// handlers for different event types in a single `HashMap` and downcast them back via
 /// [`AsAny`].
-pub trait EventTrait: AsAny {}
+pub trait EventTrait: AsAny + Send + Sync {}
 
 impl<T> EventHandler<T> {
     /// Wraps `func` as an event handler.
-    pub fn new(func: impl Fn(&World, T) + 'static) -> Self {
+    pub fn new(func: impl Fn(&World, T) + Send + Sync + 'static) -> Self {
         Self {
             func: Box::new(func),
             _marker: std::marker::PhantomData,
@@ -60,7 +62,7 @@ impl<T: 'static> AsAny for EventHandler<T> {
 #[derive(Default)]
 pub struct EventManager {
     pub events: HashMap<TypeId, Box<dyn EventTrait>>,
-    coordinator: Option<Weak<RefCell<Coordinator>>>,
+    coordinator: Option<Weak<Coordinator>>,
 }
 
 impl EventManager {
@@ -77,13 +79,13 @@ impl EventManager {
         }
     }
 
-    pub(crate) fn bind_coordinator(&mut self, coordinator: Weak<RefCell<Coordinator>>) {
+    pub(crate) fn bind_coordinator(&mut self, coordinator: Weak<Coordinator>) {
         self.coordinator = Some(coordinator);
     }
 
     /// Subscribes `event` as the handler for events of type `T`, replacing any previous
     /// handler for that type. Returns `&mut Self` for chaining.
-    pub fn subscribe_event<T: 'static, FUNC: 'static + Fn(&World, T)>(
+    pub fn subscribe_event<T: 'static, FUNC: 'static + Fn(&World, T) + Send + Sync>(
         &mut self,
         event: FUNC,
     ) -> &mut Self {
@@ -113,23 +115,32 @@ impl EventManager {
 }
 
 impl SystemParam for &EventManager {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        // Exclusive: published events run handlers that get the whole `World`.
+        access.add(
             AccessKey::Manager(TypeId::of::<EventManager>()),
             false,
             "EventManager",
         );
-        unsafe { &(*coordinator.borrow().get_event_manager_mut()) }
+        access.add_exclusive();
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &(*coordinator.get_event_manager_mut()) }
     }
 }
 
 impl SystemParam for &mut EventManager {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(TypeId::of::<EventManager>()),
             true,
             "EventManager",
         );
-        unsafe { &mut (*coordinator.borrow().get_event_manager_mut()) }
+        access.add_exclusive();
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &mut (*coordinator.get_event_manager_mut()) }
     }
 }
@@ -0,0 +1,111 @@
+use std::{
+    any::Any,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
+
+/// Object-safe equality and hashing for type-erased labels such as
+/// [`SystemLabel`]. Blanket-implemented for every `Debug + Eq + Hash` type, so string literals
+/// and plain enums can be used as labels directly.
+pub trait DynLabel: Debug + Send + Sync + 'static {
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns whether `other` is a label of the same type and value.
+    fn dyn_eq(&self, other: &dyn Any) -> bool;
+
+    /// Hashes the label's type and value.
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+impl<T: Debug + Eq + Hash + Send + Sync + 'static> DynLabel for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn dyn_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<T>() == Some(self)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        self.type_id().hash(&mut state);
+        self.hash(&mut state);
+    }
+}
+
+/// Defines a label trait blanket-implemented for every cloneable [`DynLabel`], with the
+/// `PartialEq`/`Eq`/`Hash`/`Clone` impls needed to use boxed labels as map keys.
+macro_rules! define_label {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        pub trait $name: $crate::core::label::DynLabel {
+            /// Clones the label into a new box.
+            fn dyn_clone(&self) -> Box<dyn $name>;
+        }
+
+        impl<T: $crate::core::label::DynLabel + Clone> $name for T {
+            fn dyn_clone(&self) -> Box<dyn $name> {
+                Box::new(self.clone())
+            }
+        }
+
+        impl PartialEq for dyn $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.dyn_eq(other.as_any())
+            }
+        }
+
+        impl Eq for dyn $name {}
+
+        impl std::hash::Hash for dyn $name {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.dyn_hash(state);
+            }
+        }
+
+        impl Clone for Box<dyn $name> {
+            fn clone(&self) -> Self {
+                (**self).dyn_clone()
+            }
+        }
+    };
+}
+
+pub(crate) use define_label;
+
+define_label!(
+    /// Names one or more systems so others can be ordered relative to them via
+    /// [`IntoSystemConfig::before`](super::system::IntoSystemConfig::before) and
+    /// [`IntoSystemConfig::after`](super::system::IntoSystemConfig::after). Any
+    /// `Debug + Clone + Eq + Hash` type works: `"physics"`, or a variant of your own enum.
+    SystemLabel
+);
+
+#[cfg(test)]
+mod label_test {
+    use std::collections::HashSet;
+
+    use super::SystemLabel;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Stage {
+        Input,
+        Physics,
+    }
+
+    #[test]
+    fn labels_compare_by_type_and_value() {
+        let input: Box<dyn SystemLabel> = Box::new(Stage::Input);
+        let labels = vec![
+            input.clone(),
+            Box::new(Stage::Physics),
+            Box::new("Input"),
+            input,
+        ];
+        assert!(*labels[0] == *labels[3]);
+        assert!(*labels[0] != *labels[1]);
+        assert!(*labels[0] != *labels[2]);
+
+        let unique: HashSet<_> = labels.into_iter().collect();
+        assert_eq!(unique.len(), 3);
+    }
+}
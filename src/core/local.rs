@@ -0,0 +1,112 @@
+use std::{
+    any::Any,
+    ops::{Deref, DerefMut},
+};
+
+use super::{
+    access::SystemAccess,
+    coordinator::Coordinator,
+    system::{SystemMeta, SystemParam},
+};
+
+/// The values of every [`Local`] parameter of one system, in parameter order. Each system has
+/// its own, kept in its [`SystemMeta`].
+#[derive(Default)]
+pub(crate) struct LocalStorage {
+    values: Vec<Box<dyn Any + Send>>,
+    /// Index of the value the next [`Local`] fetched during the current run gets.
+    next: usize,
+}
+
+impl LocalStorage {
+    /// Starts a new run: the system's first [`Local`] gets the first value again.
+    pub(crate) fn rewind(&mut self) {
+        self.next = 0;
+    }
+
+    /// Returns the value of the next [`Local`] parameter, created via `T::default()` the first
+    /// time the system runs.
+    fn fetch<T: Default + Send + 'static>(&mut self) -> *mut T {
+        if self.next == self.values.len() {
+            self.values.push(Box::new(T::default()));
+        }
+        let value = self.values[self.next]
+            .downcast_mut::<T>()
+            .expect("Local parameters fetched in a different order than on the first run");
+        self.next += 1;
+        value
+    }
+}
+
+/// A [`SystemParam`] holding a value of type `T` owned by the system itself: created via
+/// `T::default()` before the first run, kept between runs, and invisible to every other
+/// system (or other `Local` of the same system), so it never conflicts with anything.
+///
+/// ```
+/// # use dark_iron_ecs::core::{entity_manager::EntityManager, local::Local};
+/// fn report_every_second_frame(mut frame: Local<u64>, _: &EntityManager) {
+///     *frame += 1;
+///     if *frame % 2 == 0 {
+///         println!("frame {}", *frame);
+///     }
+/// }
+/// ```
+pub struct Local<T> {
+    value: *mut T,
+}
+
+impl<T> Deref for Local<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.value }
+    }
+}
+
+impl<T> DerefMut for Local<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.value }
+    }
+}
+
+impl<T: Default + Send + 'static> SystemParam for Local<T> {
+    fn access(_access: &mut SystemAccess) {
+        // Only the owning system ever sees the value, and it never runs twice at once.
+    }
+
+    fn get_param(_coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        Local {
+            value: meta.locals.lock().unwrap().fetch::<T>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod local_test {
+    use super::Local;
+    use crate::core::{resources::Resource, system::SystemSchedule, world::World};
+
+    #[test]
+    fn each_system_keeps_its_own_locals_between_runs() {
+        fn count(mut runs: Local<u32>, mut odd: Local<u32>, mut seen: Resource<Vec<u32>>) {
+            *runs += 1;
+            if *runs % 2 == 1 {
+                *odd += 1;
+            }
+            seen.push(*runs * 10 + *odd);
+        }
+
+        let mut world = World::new();
+        world
+            .add_resource(Vec::<u32>::new())
+            .add_systems(SystemSchedule::Update, (count, count));
+
+        world.run_update();
+        world.run_update();
+        world.run_update();
+        assert_eq!(
+            *world.get_resource::<Vec<u32>>().unwrap(),
+            [11, 11, 21, 21, 32, 32]
+        );
+    }
+}
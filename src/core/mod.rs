@@ -1,12 +1,21 @@
 //! Core ECS types. Start at [`world::World`], the main entry point for building and driving an
 //! application.
 
-/// Runtime detection of conflicting `SystemParam` accesses within a single system call.
-pub(crate) mod access;
+/// [`access::SystemAccess`], what a system's parameters access, used to detect conflicts and to
+/// run systems in parallel.
+pub mod access;
 /// Archetype-based component storage, grouping entities by their exact component-type set.
 pub mod archetype;
 /// Downcasting helper used to store heterogeneous, type-erased values (events, resources).
 pub mod as_any_trait;
+/// [`cell::AtomicRefCell`], the thread-safe `RefCell` every manager is shared through.
+pub mod cell;
+/// Per-component change ticks, and the [`change_detection::RemovedComponents`] system parameter.
+pub mod change_detection;
+/// Deferred structural changes recorded by systems through [`command::Commands`].
+pub mod command;
+/// Run [`condition::Condition`]s gating systems, and built-in conditions.
+pub mod condition;
 /// Component and component-bundle traits.
 pub mod component;
 /// [`coordinator::Coordinator`], the handle systems use to reach every manager.
@@ -19,15 +28,27 @@ pub mod entity;
 pub mod entity_manager;
 /// Error types returned by archetype/query/entity lookups.
 pub mod error;
+/// Handlers for the errors systems return, such as [`error_handler::collect_errors`].
+pub mod error_handler;
 /// Publish/subscribe event system.
 pub mod event;
 /// Reusable [`extension::Extension`] setup bundles.
 pub mod extension;
-/// Component queries, with optional [`query::Without`] filtering.
+/// Type-erased labels, such as [`label::SystemLabel`].
+pub mod label;
+/// The [`local::Local`] system parameter: per-system state kept between runs.
+pub mod local;
+/// Component queries, with optional [`query::Without`] and change-detection filtering.
 pub mod query;
 /// Global, type-keyed resources.
 pub mod resources;
-/// System registration and scheduling.
+/// [`schedule::Schedule`]: system ordering and parallel execution.
+pub mod schedule;
+/// Application [`state::State`]s, and the schedules run on their transitions.
+pub mod state;
+/// System registration.
 pub mod system;
+/// The [`time::Time`] frame clock, and the [`time::FixedTime`] fixed-timestep accumulator.
+pub mod time;
 /// [`world::World`], the ECS entry point.
 pub mod world;
@@ -1,13 +1,17 @@
-use super::access::AccessKey;
+use super::access::{AccessKey, SystemAccess};
 use super::archetype::Archetype;
+use super::change_detection::{Mut, SystemTicks};
+use super::component::{Column, Component};
 use super::coordinator::Coordinator;
-use super::error::QueryError;
-use crate::core::system::SystemParam;
+use super::entity::{Entity, EntityLocation};
+use super::entity_manager::EntityManager;
+use super::error::{QueryError, QuerySingleError};
+use crate::core::system::{SystemMeta, SystemParam};
 
 use std::any::TypeId;
-use std::cell::{RefCell, UnsafeCell};
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::sync::Arc;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub(crate) struct QuerySignature {
@@ -33,34 +37,43 @@ impl QuerySignature {
     }
 }
 
-struct QueryCache<'a> {
-    table: &'a RefCell<HashMap<QuerySignature, (u64, Vec<usize>)>>,
-    version: &'a u64,
-    signature: QuerySignature,
-}
+/// Archetypes matched by each query shape, tagged with the
+/// [`EntityManager::archetype_version`] they were computed at.
+pub(crate) type QueryCache = std::collections::HashMap<QuerySignature, (u64, Arc<[usize]>)>;
 
 /// Implemented for types (and tuples of types, up to 26 elements) that a [`Query`] can fetch —
-/// `&T` and `&mut T` for any component `T`. You don't implement this yourself.
-pub trait QueryParams<'a> {
-    type QueryResult;
+/// `&T`, `&mut T`, `Option<&T>` and `Option<&mut T>` for any component `T`. You don't implement
+/// this yourself.
+///
+/// Results and sources borrow the archetypes for `'w`: as long as the [`Query`] handing them
+/// out is borrowed, so two results reaching the same component mutably can't coexist.
+pub trait QueryParams {
+    type QueryResult<'w>;
 
     /// The resolved per-archetype data this query reads from — computed once per archetype by
     /// [`QueryParams::resolve`], then indexed per entity by
     /// [`QueryParams::get_component_from_source`], instead of re-resolving on every entity.
-    type Source;
+    type Source<'w>;
 
     /// Resolves this query's component column(s) for `archetype`. Returns `None` if the
-    /// archetype is missing a required type.
-    fn resolve(archetype: &'a Archetype) -> Option<Self::Source>;
+    /// archetype is missing a required type. Mutable fetches mark what is written through them
+    /// as changed at `change_tick`.
+    fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>>;
 
     /// Reads this query's result for one entity from an already-`resolve`d `Source`.
-    fn get_component_from_source(
-        source: &Self::Source,
+    fn get_component_from_source<'w>(
+        source: &Self::Source<'w>,
         entity_location: u32,
-    ) -> Option<Self::QueryResult>;
+    ) -> Option<Self::QueryResult<'w>>;
 
+    /// Component types an entity must have to match: every fetched type except the optional
+    /// ones (see [`QueryParams::optional_types_id`]).
     fn types_id() -> Vec<TypeId>;
 
+    /// Component types fetched through `Option<&T>`/`Option<&mut T>`: read when an entity has
+    /// them, without being required for it to match.
+    fn optional_types_id() -> Vec<TypeId>;
+
     /// Like [`QueryParams::types_id`], paired with whether each type is fetched mutably
     /// (`&mut T`) or not (`&T`). Used to detect conflicting `SystemParam` accesses.
     fn types_id_with_mutability() -> Vec<(TypeId, bool)>;
@@ -76,6 +89,16 @@ pub trait QueryConstraint {
     fn required_types() -> Vec<TypeId> {
         Vec::new()
     }
+    /// Whether [`QueryConstraint::matches_row`] can reject entities, so the number of
+    /// results isn't known up front. Defaults to `false`.
+    fn filters_rows() -> bool {
+        false
+    }
+    /// Per-entity check for the entity at `row` of an archetype that already matched by type,
+    /// given the querying system's change-tick window. Defaults to accepting every entity.
+    fn matches_row(_archetype: &Archetype, _row: usize, _ticks: SystemTicks) -> bool {
+        true
+    }
 }
 
 impl QueryConstraint for () {
@@ -140,9 +163,82 @@ impl<T: Constraints> QueryConstraint for With<T> {
     }
 }
 
+/// A [`Query`] constraint that only matches entities whose component `T` was added since the
+/// querying system last ran (for a query built via
+/// [`World::create_query_with_constraint`](super::world::World::create_query_with_constraint):
+/// ever).
+///
+/// ```
+/// # use dark_iron_ecs::core::{entity::Entity, query::{Added, Query}};
+/// # struct Health(i32);
+/// fn on_spawn(q: Query<(&Entity, &Health), Added<Health>>) {
+///     for (entity, health) in q.fetch() {
+///         println!("entity {} spawned with {} health", entity.id, health.0);
+///     }
+/// }
+/// ```
+pub struct Added<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryConstraint for Added<T> {
+    fn constraint_types() -> Vec<TypeId> {
+        Vec::new()
+    }
+
+    fn required_types() -> Vec<TypeId> {
+        vec![TypeId::of::<T>()]
+    }
+
+    fn filters_rows() -> bool {
+        true
+    }
+
+    fn matches_row(archetype: &Archetype, row: usize, ticks: SystemTicks) -> bool {
+        archetype
+            .get_ticks(TypeId::of::<T>(), row)
+            .is_some_and(|component_ticks| ticks.is_newer(component_ticks.added))
+    }
+}
+
+/// A [`Query`] constraint that only matches entities whose component `T` was added or written
+/// through a [`Mut`] — handed out by `&mut T` queries and
+/// [`Entity::get_component_mut`](super::entity::Entity::get_component_mut) — since the querying
+/// system last ran. Fetching `&mut T` without writing to it isn't a change.
+///
+/// ```
+/// # use dark_iron_ecs::core::query::{Changed, Query};
+/// # struct Position(f32, f32);
+/// fn sync_positions(q: Query<(&Position,), Changed<Position>>) {
+///     for position in q.fetch() {
+///         println!("moved to {}, {}", position.0, position.1);
+///     }
+/// }
+/// ```
+pub struct Changed<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryConstraint for Changed<T> {
+    fn constraint_types() -> Vec<TypeId> {
+        Vec::new()
+    }
+
+    fn required_types() -> Vec<TypeId> {
+        vec![TypeId::of::<T>()]
+    }
+
+    fn filters_rows() -> bool {
+        true
+    }
+
+    fn matches_row(archetype: &Archetype, row: usize, ticks: SystemTicks) -> bool {
+        archetype
+            .get_ticks(TypeId::of::<T>(), row)
+            .is_some_and(|component_ticks| ticks.is_newer(component_ticks.changed))
+    }
+}
+
 /// Reads entities that have every component type in `T` (and none of the types excluded by
-/// `Constraint`, see [`Without`]). Take one as a system parameter, or build one manually via
-/// [`World::create_query`](super::world::World::create_query).
+/// `Constraint`, see [`Without`]). Types fetched as `Option<&C>`/`Option<&mut C>` aren't
+/// required; they read as `None` for entities without a `C`. Take one as a system parameter,
+/// or build one manually via [`World::create_query`](super::world::World::create_query).
 ///
 /// ```
 /// # use dark_iron_ecs::core::query::Query;
@@ -153,53 +249,67 @@ impl<T: Constraints> QueryConstraint for With<T> {
 ///     }
 /// }
 /// ```
-pub struct Query<'a, T: QueryParams<'a> + 'static, Constraint: QueryConstraint = ()> {
-    pub archetypes: &'a Vec<Archetype>,
-    types: Vec<TypeId>,
-    excluded_types: Vec<TypeId>,
-    required_types: Vec<TypeId>,
-    cache: Option<QueryCache<'a>>,
+pub struct Query<'w, T: QueryParams + 'static, Constraint: QueryConstraint = ()> {
+    pub archetypes: &'w Vec<Archetype>,
+    entity_manager: &'w EntityManager,
+    /// Indices of the archetypes this query matches, resolved when it's built (shared with the
+    /// query cache when built through it).
+    matched: Arc<[usize]>,
+    ticks: SystemTicks,
     _marked: std::marker::PhantomData<(T, Constraint)>,
 }
 
 /// Implemented for `&T` and `&mut T` (any component `T`), letting [`Query`] fetch either
-/// shared or exclusive access to a component.
-pub trait Fetch<'a> {
-    type Result;
+/// shared or exclusive access to a component, and for their `Option` forms, fetching it only
+/// where present.
+pub trait Fetch {
+    type Result<'w>;
     /// Always a reference, so it's cheap to copy out of `&Self::Source` when reading it once
     /// per entity.
-    type Source: Copy;
+    type Source<'w>: Copy;
 
     /// Resolves the component column this fetch reads from, once per archetype instead of once
     /// per entity. Returns `None` if the archetype doesn't have this component type.
-    fn resolve(archetype: &'a Archetype) -> Option<Self::Source>;
+    fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>>;
 
     /// Reads this fetch's result for one entity from an already-`resolve`d `Source`.
-    fn fetch_from(source: Self::Source, entity_id: u32) -> Result<Self::Result, QueryError>;
+    fn fetch_from(
+        source: Self::Source<'_>,
+        entity_id: u32,
+    ) -> Result<Self::Result<'_>, QueryError>;
 
     fn get_type_id() -> TypeId;
 
     /// Whether this fetch requires mutable (`&mut T`) or shared (`&T`) access to the
     /// component.
     fn is_mutable() -> bool;
+
+    /// Whether this fetch yields `None` for entities lacking the component (`Option<&T>`,
+    /// `Option<&mut T>`) instead of excluding them from the query.
+    fn is_optional() -> bool {
+        false
+    }
 }
 
-impl<'a, T: 'static> Fetch<'a> for &mut T {
-    type Result = Self;
-    type Source = &'a UnsafeCell<Vec<T>>;
+impl<T: Component> Fetch for &mut T {
+    type Result<'w> = Mut<'w, T>;
+    /// The column, and the change tick to stamp on every component written through.
+    type Source<'w> = (&'w Column<T>, u32);
 
-    fn resolve(archetype: &'a Archetype) -> Option<Self::Source> {
-        archetype
-            .components
-            .get(&TypeId::of::<T>())?
-            .as_any()
-            .downcast_ref() as _
+    fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>> {
+        Some((archetype.column::<T>()?, change_tick))
     }
 
-    fn fetch_from(source: Self::Source, entity_id: u32) -> Result<Self::Result, QueryError> {
-        let list = unsafe { &mut *source.get() };
+    fn fetch_from(
+        (column, change_tick): Self::Source<'_>,
+        entity_id: u32,
+    ) -> Result<Self::Result<'_>, QueryError> {
+        let list = unsafe { &mut *column.data.get() };
         match list.get_mut(entity_id as usize) {
-            Some(c) => Ok(c),
+            Some(c) => {
+                let ticks = unsafe { &mut (&mut *column.ticks.get())[entity_id as usize] };
+                Ok(Mut::new(c, &mut ticks.changed, change_tick))
+            }
             None => Err(QueryError::EntityNotFound(entity_id)),
         }
     }
@@ -213,19 +323,18 @@ impl<'a, T: 'static> Fetch<'a> for &mut T {
     }
 }
 
-impl<'a, T: 'static> Fetch<'a> for &T {
-    type Result = Self;
-    type Source = &'a UnsafeCell<Vec<T>>;
+impl<T: Component> Fetch for &T {
+    type Result<'w> = &'w T;
+    type Source<'w> = &'w UnsafeCell<Vec<T>>;
 
-    fn resolve(archetype: &'a Archetype) -> Option<Self::Source> {
-        archetype
-            .components
-            .get(&TypeId::of::<T>())?
-            .as_any()
-            .downcast_ref()
+    fn resolve(archetype: &Archetype, _change_tick: u32) -> Option<Self::Source<'_>> {
+        Some(&archetype.column::<T>()?.data)
     }
 
-    fn fetch_from(source: Self::Source, entity_id: u32) -> Result<Self::Result, QueryError> {
+    fn fetch_from(
+        source: Self::Source<'_>,
+        entity_id: u32,
+    ) -> Result<Self::Result<'_>, QueryError> {
         let list = unsafe { &*source.get() };
         match list.get(entity_id as usize) {
             Some(c) => Ok(c),
@@ -242,23 +351,99 @@ impl<'a, T: 'static> Fetch<'a> for &T {
     }
 }
 
-impl<'a, T: Fetch<'a> + 'static> QueryParams<'a> for T {
-    type QueryResult = T::Result;
-    type Source = <T as Fetch<'a>>::Source;
+/// Resolves the inner fetch where the archetype has the component, and `None` where it doesn't.
+impl<T: Component> Fetch for Option<&T> {
+    type Result<'w> = Option<&'w T>;
+    type Source<'w> = Option<<&'static T as Fetch>::Source<'w>>;
+
+    fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>> {
+        Some(<&T as Fetch>::resolve(archetype, change_tick))
+    }
+
+    fn fetch_from(
+        source: Self::Source<'_>,
+        entity_id: u32,
+    ) -> Result<Self::Result<'_>, QueryError> {
+        match source {
+            Some(source) => <&T as Fetch>::fetch_from(source, entity_id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn get_type_id() -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn is_mutable() -> bool {
+        false
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+}
+
+/// Mutable counterpart to the `Option<&T>` fetch, yielding `Option<Mut<T>>`.
+impl<T: Component> Fetch for Option<&mut T> {
+    type Result<'w> = Option<Mut<'w, T>>;
+    type Source<'w> = Option<<&'static mut T as Fetch>::Source<'w>>;
+
+    fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>> {
+        Some(<&mut T as Fetch>::resolve(archetype, change_tick))
+    }
+
+    fn fetch_from(
+        source: Self::Source<'_>,
+        entity_id: u32,
+    ) -> Result<Self::Result<'_>, QueryError> {
+        match source {
+            Some(source) => <&mut T as Fetch>::fetch_from(source, entity_id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn get_type_id() -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn is_mutable() -> bool {
+        true
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+}
+
+impl<T: Fetch + 'static> QueryParams for T {
+    type QueryResult<'w> = T::Result<'w>;
+    type Source<'w> = <T as Fetch>::Source<'w>;
 
-    fn resolve(archetype: &'a Archetype) -> Option<Self::Source> {
-        <T as Fetch>::resolve(archetype)
+    fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>> {
+        <T as Fetch>::resolve(archetype, change_tick)
     }
 
-    fn get_component_from_source(
-        source: &Self::Source,
+    fn get_component_from_source<'w>(
+        source: &Self::Source<'w>,
         entity_location: u32,
-    ) -> Option<Self::QueryResult> {
+    ) -> Option<Self::QueryResult<'w>> {
         <T as Fetch>::fetch_from(*source, entity_location).ok()
     }
 
     fn types_id() -> Vec<TypeId> {
-        vec![<T>::get_type_id()]
+        if <T>::is_optional() {
+            Vec::new()
+        } else {
+            vec![<T>::get_type_id()]
+        }
+    }
+
+    fn optional_types_id() -> Vec<TypeId> {
+        if <T>::is_optional() {
+            vec![<T>::get_type_id()]
+        } else {
+            Vec::new()
+        }
     }
 
     fn types_id_with_mutability() -> Vec<(TypeId, bool)> {
@@ -266,7 +451,7 @@ impl<'a, T: Fetch<'a> + 'static> QueryParams<'a> for T {
     }
 }
 
-impl<T: for<'a> Fetch<'a> + 'static> Constraints for T {
+impl<T: Fetch + 'static> Constraints for T {
     fn constraint_types() -> Vec<TypeId> {
         vec![<T>::get_type_id()]
     }
@@ -274,23 +459,27 @@ impl<T: for<'a> Fetch<'a> + 'static> Constraints for T {
 
 macro_rules! impl_query_params {
     ( $head:ident ) => {
-        impl<'a, $head: Fetch<'a> + 'static> QueryParams<'a> for ($head,) {
-            type QueryResult = $head::Result;
-            type Source = <$head as Fetch<'a>>::Source;
+        impl<$head: Fetch + 'static> QueryParams for ($head,) {
+            type QueryResult<'w> = $head::Result<'w>;
+            type Source<'w> = <$head as Fetch>::Source<'w>;
 
-            fn resolve(archetype: &'a Archetype) -> Option<Self::Source> {
-                <$head as Fetch>::resolve(archetype)
+            fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>> {
+                <$head as Fetch>::resolve(archetype, change_tick)
             }
 
-            fn get_component_from_source(
-                source: &Self::Source,
+            fn get_component_from_source<'w>(
+                source: &Self::Source<'w>,
                 entity_location: u32,
-            ) -> Option<Self::QueryResult> {
+            ) -> Option<Self::QueryResult<'w>> {
                 <$head as Fetch>::fetch_from(*source, entity_location).ok()
             }
 
             fn types_id() -> Vec<TypeId> {
-                vec![<$head>::get_type_id()]
+                <$head as QueryParams>::types_id()
+            }
+
+            fn optional_types_id() -> Vec<TypeId> {
+                <$head as QueryParams>::optional_types_id()
             }
 
             fn types_id_with_mutability() -> Vec<(TypeId, bool)> {
@@ -303,21 +492,23 @@ macro_rules! impl_query_params {
     };
     ( $head:ident, $($tail:ident),+ ) => {
         #[allow(non_snake_case)]
-        impl<'a, $head: Fetch<'a>  + 'static, $($tail: Fetch<'a>  + 'static),+> QueryParams<'a> for ($head, $($tail),+) {
-            type QueryResult = ($head::Result, $($tail::Result),+);
-            type Source = (<$head as Fetch<'a>>::Source, $(<$tail as Fetch<'a>>::Source),+);
+        impl<$head: Fetch + 'static, $($tail: Fetch + 'static),+> QueryParams
+            for ($head, $($tail),+)
+        {
+            type QueryResult<'w> = ($head::Result<'w>, $($tail::Result<'w>),+);
+            type Source<'w> = (<$head as Fetch>::Source<'w>, $(<$tail as Fetch>::Source<'w>),+);
 
-            fn resolve(archetype: &'a Archetype) -> Option<Self::Source> {
+            fn resolve(archetype: &Archetype, change_tick: u32) -> Option<Self::Source<'_>> {
                 Some((
-                    <$head as Fetch>::resolve(archetype)?,
-                    $(<$tail as Fetch>::resolve(archetype)?),+
+                    <$head as Fetch>::resolve(archetype, change_tick)?,
+                    $(<$tail as Fetch>::resolve(archetype, change_tick)?),+
                 ))
             }
 
-            fn get_component_from_source(
-                source: &Self::Source,
+            fn get_component_from_source<'w>(
+                source: &Self::Source<'w>,
                 entity_location: u32,
-            ) -> Option<Self::QueryResult> {
+            ) -> Option<Self::QueryResult<'w>> {
                 let ($head, $($tail),+) = source;
                 Some((
                     <$head as Fetch>::fetch_from(*$head, entity_location).ok()?,
@@ -326,7 +517,14 @@ macro_rules! impl_query_params {
             }
 
             fn types_id() -> Vec<TypeId> {
-                let types = vec![<$head>::get_type_id(), $($tail::get_type_id()),+];
+                let mut types = <$head as QueryParams>::types_id();
+                $(types.extend(<$tail as QueryParams>::types_id());)+
+                types
+            }
+
+            fn optional_types_id() -> Vec<TypeId> {
+                let mut types = <$head as QueryParams>::optional_types_id();
+                $(types.extend(<$tail as QueryParams>::optional_types_id());)+
                 types
             }
 
@@ -348,14 +546,16 @@ impl_query_params!(
 
 macro_rules! impl_query_constrains {
     ( $head:ident ) => {
-        impl<$head: for<'a> Fetch<'a>  + 'static > Constraints for ($head,) {
+        impl<$head: Fetch  + 'static > Constraints for ($head,) {
             fn constraint_types() -> Vec<TypeId> {
                 vec![<$head>::get_type_id()]
             }
         }
     };
     ( $head:ident, $($tail:ident),+ ) => {
-        impl<$head: for<'a> Fetch<'a>  + 'static, $($tail: for<'a> Fetch<'a> + 'static),+> Constraints for ($head, $($tail),+) {
+        impl<$head: Fetch + 'static, $($tail: Fetch + 'static),+> Constraints
+            for ($head, $($tail),+)
+        {
             fn constraint_types() -> Vec<TypeId> {
                 vec![<$head>::get_type_id(), $($tail::get_type_id()),+]
             }
@@ -368,105 +568,285 @@ impl_query_constrains!(
     A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z
 );
 
-impl<'a, T: QueryParams<'a> + 'static, Constraint: QueryConstraint> Query<'a, T, Constraint> {
-    /// Builds a query over the given archetype list. Usually not called directly — prefer
-    /// [`World::create_query`](super::world::World::create_query) or taking a `Query` as a
-    /// system parameter.
-    pub fn new(archetypes: &'a Vec<Archetype>) -> Query<'a, T, Constraint> {
+impl<'w, T: QueryParams + 'static, Constraint: QueryConstraint> Query<'w, T, Constraint> {
+    /// Builds a read-only query over `entity_manager`'s archetypes. Usually not called directly
+    /// — prefer [`World::create_query`](super::world::World::create_query) or taking a `Query`
+    /// as a system parameter.
+    ///
+    /// Change-detection constraints ([`Added`], [`Changed`]) see every component as new.
+    pub fn new(entity_manager: &'w EntityManager) -> Query<'w, T, Constraint>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        Self::scan(entity_manager)
+    }
+
+    /// Like [`Query::new`], for queries that fetch components mutably: borrowing
+    /// `entity_manager` mutably keeps two such queries from handing out the same component.
+    /// `&mut T` fetches are stamped with the manager's current change tick.
+    ///
+    /// ```compile_fail
+    /// # use dark_iron_ecs::core::{entity_manager::EntityManager, query::Query};
+    /// # struct Health(u32);
+    /// let mut em = EntityManager::new();
+    /// let mut q1 = Query::<&mut Health>::new_mut(&mut em);
+    /// let mut q2 = Query::<&mut Health>::new_mut(&mut em);
+    /// let _ = (q1.fetch_mut(), q2.fetch_mut());
+    /// ```
+    pub fn new_mut(entity_manager: &'w mut EntityManager) -> Query<'w, T, Constraint> {
+        Self::scan(entity_manager)
+    }
+
+    fn scan(entity_manager: &'w EntityManager) -> Query<'w, T, Constraint> {
         Query {
-            archetypes,
-            types: T::types_id(),
-            excluded_types: Constraint::constraint_types(),
-            required_types: Constraint::required_types(),
-            cache: None,
+            archetypes: &entity_manager.archetypes,
+            entity_manager,
+            matched: Self::scan_matching_archetypes(&entity_manager.archetypes).into(),
+            ticks: SystemTicks::first_run(entity_manager.change_tick),
             _marked: std::marker::PhantomData,
         }
     }
 
+    /// Like [`Query::new`], but matching archetypes through `entity_manager`'s query cache, and
+    /// within the change-tick window `ticks` of the system it's handed to.
     pub(crate) fn new_with_cache(
-        archetypes: &'a Vec<Archetype>,
-        table: &'a RefCell<HashMap<QuerySignature, (u64, Vec<usize>)>>,
-        version: &'a u64,
-    ) -> Query<'a, T, Constraint> {
-        let types = T::types_id();
-        let excluded_types = Constraint::constraint_types();
-        let required_types = Constraint::required_types();
+        entity_manager: &'w EntityManager,
+        ticks: SystemTicks,
+    ) -> Query<'w, T, Constraint> {
         let signature = QuerySignature::new(
-            types.clone(),
-            excluded_types.clone(),
-            required_types.clone(),
+            T::types_id(),
+            Constraint::constraint_types(),
+            Constraint::required_types(),
         );
+        let version = entity_manager.archetype_version;
+        let mut table = entity_manager.query_cache.lock().unwrap();
+        let matched = match table.get(&signature) {
+            Some((seen_version, indices)) if *seen_version == version => indices.clone(),
+            _ => {
+                let indices: Arc<[usize]> =
+                    Self::scan_matching_archetypes(&entity_manager.archetypes).into();
+                table.insert(signature, (version, indices.clone()));
+                indices
+            }
+        };
         Query {
-            archetypes,
-            types,
-            excluded_types,
-            required_types,
-            cache: Some(QueryCache {
-                table,
-                version,
-                signature,
-            }),
+            archetypes: &entity_manager.archetypes,
+            entity_manager,
+            matched,
+            ticks,
             _marked: std::marker::PhantomData,
         }
     }
 
-    /// Runs the query, returning one result per matching entity.
-    pub fn fetch(&self) -> Vec<<T as QueryParams<'a>>::QueryResult> {
-        let matching_indices: Vec<usize> = match &self.cache {
-            Some(cache) => {
-                let mut table = cache.table.borrow_mut();
-                match table.get(&cache.signature) {
-                    Some((seen_version, indices)) if *seen_version == *cache.version => {
-                        indices.clone()
-                    }
-                    _ => {
-                        let indices = self.scan_matching_archetypes();
-                        table.insert(cache.signature.clone(), (*cache.version, indices.clone()));
-                        indices
-                    }
-                }
-            }
-            None => self.scan_matching_archetypes(),
-        };
+    /// Runs a read-only query, collecting one result per matching entity into a `Vec`. Prefer
+    /// [`Query::iter`], which doesn't allocate.
+    pub fn fetch(&self) -> Vec<T::QueryResult<'w>>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        self.query_iter().collect()
+    }
 
-        let mut components = Vec::new();
-        for &arch_index in &matching_indices {
-            let arch = &self.archetypes[arch_index];
-            // Resolves every component column this query needs *once* for this archetype,
-            // instead of re-resolving them on every entity below.
-            if let Some(source) = T::resolve(arch) {
-                for (index, _) in arch.entities.iter().enumerate() {
-                    if let Some(component) = T::get_component_from_source(&source, index as u32) {
-                        components.push(component);
-                    }
-                }
+    /// Like [`Query::fetch`], for queries that fetch components mutably. Prefer
+    /// [`Query::iter_mut`], which doesn't allocate.
+    pub fn fetch_mut(&mut self) -> Vec<T::QueryResult<'_>> {
+        self.query_iter().collect()
+    }
+
+    /// Iterates the results of a read-only query (only `&T`/`Option<&T>` fetches) lazily,
+    /// archetype by archetype, without allocating. Shared results never alias a mutable one, so
+    /// they may outlive the borrow of the query.
+    pub fn iter(&self) -> QueryIter<'w, T, Constraint>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        self.query_iter()
+    }
+
+    /// Like [`Query::iter`], for queries that fetch components mutably. The results borrow the
+    /// query mutably, so they can't outlive the iteration or coexist with other results of it.
+    pub fn iter_mut(&mut self) -> QueryIter<'_, T, Constraint> {
+        self.query_iter()
+    }
+
+    /// Returns `entity`'s result, if the query matches it. Errors if `entity` doesn't exist, is
+    /// stale, or isn't matched by the query (see [`QueryError::QueryDoesNotMatch`]).
+    pub fn get(&self, entity: Entity) -> Result<T::QueryResult<'w>, QueryError>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        self.locate_match(entity)
+            .map(|location| self.fetch_at(location))
+    }
+
+    /// Like [`Query::get`], for queries that fetch components mutably. The result borrows the
+    /// query mutably, so it can't coexist with any other result of it:
+    ///
+    /// ```compile_fail
+    /// # use dark_iron_ecs::core::{entity::Entity, query::Query};
+    /// # struct Health(u32);
+    /// fn alias(mut q: Query<&mut Health>, entity: Entity) {
+    ///     let first = q.get_mut(entity).unwrap();
+    ///     let again = q.get_mut(entity).unwrap();
+    ///     let _ = (first.0, again.0);
+    /// }
+    /// ```
+    pub fn get_mut(&mut self, entity: Entity) -> Result<T::QueryResult<'_>, QueryError> {
+        self.locate_match(entity)
+            .map(|location| self.fetch_at(location))
+    }
+
+    /// Like [`Query::get`], for several entities at once. Fails on the first entity the query
+    /// doesn't match.
+    pub fn get_many<const N: usize>(
+        &self,
+        entities: [Entity; N],
+    ) -> Result<[T::QueryResult<'w>; N], QueryError>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        let locations = self.locate_matches(&entities)?;
+        Ok(locations.map(|location| self.fetch_at(location)))
+    }
+
+    /// Like [`Query::get_many`], for queries that fetch components mutably. Errors with
+    /// [`QueryError::DuplicateEntity`] if an entity is listed twice, rather than handing out two
+    /// mutable references to its components. Like [`Query::get_mut`], the results borrow the
+    /// query mutably:
+    ///
+    /// ```compile_fail
+    /// # use dark_iron_ecs::core::{entity::Entity, query::Query};
+    /// # struct Health(u32);
+    /// fn alias(mut q: Query<&mut Health>, a: Entity, b: Entity) {
+    ///     let [first, _] = q.get_many_mut([a, b]).unwrap();
+    ///     let again = q.get_mut(a).unwrap();
+    ///     let _ = (first.0, again.0);
+    /// }
+    /// ```
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> Result<[T::QueryResult<'_>; N], QueryError> {
+        for (index, entity) in entities.iter().enumerate() {
+            if entities[..index].iter().any(|other| other.id == entity.id) {
+                return Err(QueryError::DuplicateEntity(entity.id));
             }
         }
-        components
+        let locations = self.locate_matches(&entities)?;
+        Ok(locations.map(|location| self.fetch_at(location)))
+    }
+
+    /// Returns the result for the only entity this query matches, e.g. the player or the camera.
+    /// Errors if it matches no entity or several.
+    pub fn get_single(&self) -> Result<T::QueryResult<'w>, QuerySingleError>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        Self::only_item(self.query_iter())
+    }
+
+    /// Like [`Query::get_single`], for queries that fetch components mutably.
+    pub fn get_single_mut(&mut self) -> Result<T::QueryResult<'_>, QuerySingleError> {
+        Self::only_item(self.query_iter())
+    }
+
+    /// Like [`Query::get_single`], but panics (with the [`QuerySingleError`] message) instead of
+    /// returning an error.
+    pub fn single(&self) -> T::QueryResult<'w>
+    where
+        T: ReadOnlyQueryParams,
+    {
+        self.get_single().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`Query::single`], for queries that fetch components mutably.
+    pub fn single_mut(&mut self) -> T::QueryResult<'_> {
+        self.get_single_mut()
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn only_item<'s>(
+        mut iter: QueryIter<'s, T, Constraint>,
+    ) -> Result<T::QueryResult<'s>, QuerySingleError> {
+        let query = std::any::type_name::<T>();
+        let item = iter.next().ok_or(QuerySingleError::NoEntities(query))?;
+        match iter.next() {
+            Some(_) => Err(QuerySingleError::MultipleEntities(query)),
+            None => Ok(item),
+        }
+    }
+
+    /// Returns where `entity` lives, checking the query matches it.
+    fn locate_match(&self, entity: Entity) -> Result<EntityLocation, QueryError> {
+        let location = self.entity_manager.locate(entity)?;
+        // `matched` is built in ascending archetype order.
+        let archetype_matches = self.matched.binary_search(&location.archetype).is_ok();
+        if !archetype_matches
+            || !Constraint::matches_row(
+                &self.archetypes[location.archetype],
+                location.row,
+                self.ticks,
+            )
+        {
+            return Err(QueryError::QueryDoesNotMatch(entity.id));
+        }
+        Ok(location)
     }
 
-    fn scan_matching_archetypes(&self) -> Vec<usize> {
-        self.archetypes
+    fn locate_matches<const N: usize>(
+        &self,
+        entities: &[Entity; N],
+    ) -> Result<[EntityLocation; N], QueryError> {
+        let mut locations = [EntityLocation {
+            archetype: 0,
+            row: 0,
+        }; N];
+        for (location, entity) in locations.iter_mut().zip(entities) {
+            *location = self.locate_match(*entity)?;
+        }
+        Ok(locations)
+    }
+
+    /// Fetches the result at a `location` already checked by [`Query::locate_match`].
+    /// Like [`Query::query_iter`], callers handing out mutable results must tie `'s` to a mutable
+    /// borrow of the query.
+    fn fetch_at<'s>(&self, location: EntityLocation) -> T::QueryResult<'s>
+    where
+        'w: 's,
+    {
+        let archetype = &self.archetypes[location.archetype];
+        T::resolve(archetype, self.ticks.this_run)
+            .and_then(|source| T::get_component_from_source(&source, location.row as u32))
+            .expect("a matched archetype has every column the query fetches")
+    }
+
+    /// Iterates the results for `'s`, through a shared borrow of the query: callers handing out
+    /// mutable results must tie `'s` to a mutable borrow of it.
+    fn query_iter<'s>(&self) -> QueryIter<'s, T, Constraint>
+    where
+        'w: 's,
+    {
+        QueryIter::new(self.archetypes, self.matched.clone(), self.ticks)
+    }
+
+    fn scan_matching_archetypes(archetypes: &[Archetype]) -> Vec<usize> {
+        let types = T::types_id();
+        let excluded_types = Constraint::constraint_types();
+        let required_types = Constraint::required_types();
+        archetypes
             .iter()
             .enumerate()
             .filter_map(|(index, arch)| {
-                let has_any_entities = arch.entities.is_empty();
-                let query_needs_more_types_than_archetype_has =
-                    self.types.len() > arch.components.len();
-                let has_constraint = self
-                    .excluded_types
-                    .iter()
-                    .any(|type_id| arch.has_type(*type_id));
-                let is_missing = self
-                    .required_types
+                // Empty archetypes still match: they're kept around (see
+                // `EntityManager::compact`) and may be refilled without the archetype version
+                // changing, so the cached indices must not depend on occupancy.
+                let has_constraint = excluded_types.iter().any(|type_id| arch.has_type(*type_id));
+                let is_missing = types
                     .iter()
+                    .chain(&required_types)
                     .any(|type_id| !arch.has_type(*type_id));
 
-                if has_any_entities
-                    || query_needs_more_types_than_archetype_has
-                    || has_constraint
-                    || is_missing
-                {
+                if has_constraint || is_missing {
                     None
                 } else {
                     Some(index)
@@ -476,43 +856,196 @@ impl<'a, T: QueryParams<'a> + 'static, Constraint: QueryConstraint> Query<'a, T,
     }
 }
 
-impl<'a, T: QueryParams<'a> + 'static, Constraint: QueryConstraint> IntoIterator
-    for Query<'a, T, Constraint>
+/// Marker for the [`Fetch`]es that only read their component (`&T`, `Option<&T>`).
+pub trait ReadOnlyFetch: Fetch {}
+
+impl<T: Component> ReadOnlyFetch for &T {}
+impl<T: Component> ReadOnlyFetch for Option<&T> {}
+
+/// Marker for the [`QueryParams`] made only of [`ReadOnlyFetch`]es, which [`Query::iter`] can
+/// hand out through a shared reference to the query.
+pub trait ReadOnlyQueryParams: QueryParams {}
+
+impl<T: ReadOnlyFetch + 'static> ReadOnlyQueryParams for T {}
+
+macro_rules! impl_read_only_query_params {
+    ( $head:ident ) => {
+        impl<$head: ReadOnlyFetch + 'static> ReadOnlyQueryParams for ($head,) {}
+    };
+    ( $head:ident, $($tail:ident),+ ) => {
+        impl<$head: ReadOnlyFetch + 'static, $($tail: ReadOnlyFetch + 'static),+>
+            ReadOnlyQueryParams for ($head, $($tail),+)
+        {
+        }
+        impl_read_only_query_params!($($tail),+);
+    };
+}
+impl_read_only_query_params!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z
+);
+
+/// Marker for the [`QueryConstraint`]s that match whole archetypes (`()`, [`With`],
+/// [`Without`]), never individual entities, so a [`QueryIter`] knows its exact length.
+pub trait ArchetypeConstraint: QueryConstraint {}
+
+impl ArchetypeConstraint for () {}
+impl<T: Constraints> ArchetypeConstraint for With<T> {}
+impl<T: Constraints> ArchetypeConstraint for Without<T> {}
+
+/// Lazy iterator over a [`Query`]'s results, returned by [`Query::iter`], [`Query::iter_mut`]
+/// and `into_iter`. Walks the matched archetypes row by row, resolving each archetype's columns
+/// once when it reaches it.
+///
+/// Its results live for `'s`: the mutable borrow of the query it iterates if any result is
+/// mutable, so they can't outlive that borrow, or the query's own lifetime otherwise:
+///
+/// ```compile_fail
+/// # use dark_iron_ecs::core::query::Query;
+/// # struct Health(i32);
+/// fn alias(mut q: Query<&mut Health>) {
+///     let first = q.iter_mut().next().unwrap();
+///     let again = q.iter_mut().next().unwrap();
+///     let _ = (first.0, again.0);
+/// }
+/// ```
+pub struct QueryIter<'s, T: QueryParams + 'static, Constraint: QueryConstraint = ()> {
+    archetypes: &'s [Archetype],
+    matched: Arc<[usize]>,
+    /// Position in `matched` of the next archetype to visit.
+    next_match: usize,
+    /// The archetype being walked, with its resolved columns.
+    current: Option<(&'s Archetype, T::Source<'s>)>,
+    /// Next row of `current` to visit.
+    row: usize,
+    /// Rows left to visit, in `current` and every archetype after it.
+    remaining_rows: usize,
+    ticks: SystemTicks,
+    _marked: std::marker::PhantomData<Constraint>,
+}
+
+impl<'s, T: QueryParams + 'static, Constraint: QueryConstraint> QueryIter<'s, T, Constraint> {
+    fn new(archetypes: &'s [Archetype], matched: Arc<[usize]>, ticks: SystemTicks) -> Self {
+        let remaining_rows = matched
+            .iter()
+            .map(|&index| archetypes[index].entities.len())
+            .sum();
+        QueryIter {
+            archetypes,
+            matched,
+            next_match: 0,
+            current: None,
+            row: 0,
+            remaining_rows,
+            ticks,
+            _marked: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'s, T: QueryParams + 'static, Constraint: QueryConstraint> Iterator
+    for QueryIter<'s, T, Constraint>
+{
+    type Item = T::QueryResult<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((archetype, source)) = &self.current {
+                while self.row < archetype.entities.len() {
+                    let row = self.row;
+                    self.row += 1;
+                    self.remaining_rows -= 1;
+                    // Filtered before fetching, so skipped entities aren't marked as changed.
+                    if !Constraint::matches_row(archetype, row, self.ticks) {
+                        continue;
+                    }
+                    if let Some(item) = T::get_component_from_source(source, row as u32) {
+                        return Some(item);
+                    }
+                }
+            }
+
+            let &index = self.matched.get(self.next_match)?;
+            self.next_match += 1;
+            let archetype = &self.archetypes[index];
+            self.row = 0;
+            self.current = match T::resolve(archetype, self.ticks.this_run) {
+                Some(source) => Some((archetype, source)),
+                None => {
+                    self.remaining_rows -= archetype.entities.len();
+                    None
+                }
+            };
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if Constraint::filters_rows() {
+            (0, Some(self.remaining_rows))
+        } else {
+            (self.remaining_rows, Some(self.remaining_rows))
+        }
+    }
+}
+
+impl<T: QueryParams + 'static, Constraint: ArchetypeConstraint> ExactSizeIterator
+    for QueryIter<'_, T, Constraint>
+{
+}
+
+impl<'w, T: QueryParams + 'static, Constraint: QueryConstraint> IntoIterator
+    for Query<'w, T, Constraint>
+{
+    type Item = T::QueryResult<'w>;
+    type IntoIter = QueryIter<'w, T, Constraint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        QueryIter::new(self.archetypes, self.matched, self.ticks)
+    }
+}
+
+impl<'w, T: ReadOnlyQueryParams + 'static, Constraint: QueryConstraint> IntoIterator
+    for &Query<'w, T, Constraint>
 {
-    type Item = <T as QueryParams<'a>>::QueryResult;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type Item = T::QueryResult<'w>;
+    type IntoIter = QueryIter<'w, T, Constraint>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.fetch().into_iter()
+        self.iter()
     }
 }
 
-impl<'a, T: QueryParams<'a>, Constraint: QueryConstraint + 'static> SystemParam
-    for Query<'a, T, Constraint>
+impl<'s, T: QueryParams + 'static, Constraint: QueryConstraint> IntoIterator
+    for &'s mut Query<'_, T, Constraint>
 {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        {
-            let coordinator_ref = coordinator.borrow();
-            let mut tracker = coordinator_ref.access_tracker.borrow_mut();
-            tracker.track(
-                AccessKey::Manager(TypeId::of::<super::entity_manager::EntityManager>()),
-                false,
-                "EntityManager (via Query)",
-            );
-            tracker.track_query(
-                T::types_id_with_mutability(),
-                Constraint::required_types(),
-                Constraint::constraint_types(),
-                std::any::type_name::<T>(),
-            );
-        }
-        let entity_manager: Rc<RefCell<super::entity_manager::EntityManager>> =
-            coordinator.borrow().entity_manager.clone();
-        let ptr = entity_manager.as_ptr();
-        let archetypes = unsafe { &(*ptr).archetypes };
-        let table = unsafe { &(*ptr).query_cache };
-        let version = unsafe { &(*ptr).archetype_version };
-        Query::<T, Constraint>::new_with_cache(archetypes, table, version)
+    type Item = T::QueryResult<'s>;
+    type IntoIter = QueryIter<'s, T, Constraint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: QueryParams + 'static, Constraint: QueryConstraint + 'static> SystemParam
+    for Query<'_, T, Constraint>
+{
+    fn access(access: &mut SystemAccess) {
+        access.add(
+            AccessKey::Manager(TypeId::of::<EntityManager>()),
+            false,
+            "EntityManager (via Query)",
+        );
+        access.add_query(
+            T::types_id_with_mutability(),
+            T::optional_types_id(),
+            Constraint::required_types(),
+            Constraint::constraint_types(),
+            std::any::type_name::<T>(),
+        );
+    }
+
+    fn get_param(coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        let entity_manager = unsafe { &*coordinator.get_entity_manager_mut() };
+        Query::<T, Constraint>::new_with_cache(entity_manager, meta.ticks)
     }
 }
 
@@ -529,8 +1062,7 @@ mod query_test {
         em.create_entity((Health(1),));
         em.create_entity((Health(2),));
 
-        let archetypes = &em.archetypes;
-        let q = Query::<&Health>::new(archetypes);
+        let q = Query::<&Health>::new(&em);
         let mut total = 0;
         for h in q {
             total += h.0;
@@ -552,8 +1084,8 @@ mod query_test {
         #[derive(Debug)]
         #[allow(dead_code)]
         pub struct Name(String);
-        let v = vec![];
-        let q = Query::<&Health>::new(&v);
+        let em = EntityManager::new();
+        let q = Query::<&Health>::new(&em);
         for h in q.fetch() {
             println!("{:?}", h);
         }
@@ -567,24 +1099,18 @@ mod query_test {
         let mut em = EntityManager::new();
         em.create_entity((Health(1),));
 
-        let table = RefCell::new(HashMap::new());
-
         {
-            let version = em.archetype_version;
-            let archetypes = &em.archetypes;
-            let q = Query::<&Health>::new_with_cache(archetypes, &table, &version);
+            let q = Query::<&Health>::new_with_cache(&em, SystemTicks::default());
             assert_eq!(q.fetch().len(), 1);
         }
-        assert_eq!(table.borrow().len(), 1);
+        assert_eq!(em.query_cache.lock().unwrap().len(), 1);
 
         {
-            let version = em.archetype_version;
-            let archetypes = &em.archetypes;
-            let q = Query::<&Health>::new_with_cache(archetypes, &table, &version);
+            let q = Query::<&Health>::new_with_cache(&em, SystemTicks::default());
             assert_eq!(q.fetch().len(), 1);
         }
         assert_eq!(
-            table.borrow().len(),
+            em.query_cache.lock().unwrap().len(),
             1,
             "second call must reuse the cached entry, not add a new one"
         );
@@ -600,21 +1126,15 @@ mod query_test {
         let mut em = EntityManager::new();
         em.create_entity((Health(1),));
 
-        let table = RefCell::new(HashMap::new());
-
         {
-            let version = em.archetype_version;
-            let archetypes = &em.archetypes;
-            let q = Query::<&Health>::new_with_cache(archetypes, &table, &version);
+            let q = Query::<&Health>::new_with_cache(&em, SystemTicks::default());
             assert_eq!(q.fetch().len(), 1);
         }
 
         // New archetype shape -> bumps archetype_version.
         em.create_entity((Health(2), Name("x".into())));
 
-        let version = em.archetype_version;
-        let archetypes = &em.archetypes;
-        let q = Query::<&Health>::new_with_cache(archetypes, &table, &version);
+        let q = Query::<&Health>::new_with_cache(&em, SystemTicks::default());
         let results = q.fetch();
         assert_eq!(
             results.len(),
@@ -622,4 +1142,328 @@ mod query_test {
             "cache must pick up the new archetype after invalidation"
         );
     }
+
+    #[test]
+    fn query_cache_still_matches_an_archetype_refilled_after_emptying() {
+        #[allow(dead_code)]
+        struct Health(i32);
+        #[allow(dead_code)]
+        struct Name(String);
+
+        let mut em = EntityManager::new();
+        let entity = em.create_entity((Health(1),));
+        em.add_component_to_entity(entity, Name("x".into()))
+            .unwrap();
+
+        {
+            let q = Query::<&Health, Without<&Name>>::new_with_cache(&em, SystemTicks::default());
+            assert_eq!(q.fetch().len(), 0);
+        }
+
+        // Refills the emptied (Health,) archetype without creating a new shape.
+        em.remove_component::<Name>(entity).unwrap();
+
+        let q = Query::<&Health, Without<&Name>>::new_with_cache(&em, SystemTicks::default());
+        assert_eq!(q.fetch().len(), 1);
+    }
+
+    #[test]
+    fn added_and_changed_only_report_what_is_new_to_the_system() {
+        use crate::core::{command::Commands, system::SystemSchedule, world::World};
+
+        struct Health(i32);
+        struct Seen {
+            added: usize,
+            changed: usize,
+        }
+
+        fn report(
+            added: Query<(&Health,), Added<Health>>,
+            changed: Query<(&Health,), Changed<Health>>,
+            mut commands: Commands,
+        ) {
+            commands.add_resource(Seen {
+                added: added.fetch().len(),
+                changed: changed.fetch().len(),
+            });
+        }
+        let seen = |world: &World| {
+            let seen = world.get_resource::<Seen>().unwrap();
+            (seen.added, seen.changed)
+        };
+
+        let mut world = World::new();
+        let mut entity = world.create_entity_with_id((Health(1),));
+        world.add_system(SystemSchedule::Update, report);
+
+        world.run_update();
+        assert_eq!(seen(&world), (1, 1));
+        world.run_update();
+        assert_eq!(seen(&world), (0, 0));
+
+        entity.get_component_mut::<Health>(&mut world).unwrap().0 = 2;
+        world.create_entity((Health(5),));
+        world.run_update();
+        assert_eq!(seen(&world), (1, 2));
+        world.run_update();
+        assert_eq!(seen(&world), (0, 0));
+
+        // Reading through `get_component_mut` isn't a change, replacing the component is.
+        assert_eq!(entity.get_component_mut::<Health>(&mut world).unwrap().0, 2);
+        world.run_update();
+        assert_eq!(seen(&world), (0, 0));
+        world.add_component_to_entity(entity, Health(3));
+        world.run_update();
+        assert_eq!(seen(&world), (0, 1));
+    }
+
+    #[test]
+    fn mutable_fetch_marks_only_the_matched_entities_as_changed() {
+        use crate::core::{command::Commands, system::SystemSchedule, world::World};
+
+        struct Health(i32);
+        struct Poisoned;
+
+        fn poison(mut q: Query<(&mut Health,), With<&Poisoned>>) {
+            for mut health in q.iter_mut() {
+                health.0 -= 1;
+            }
+        }
+        fn count_changed(q: Query<(&Health,), Changed<Health>>, mut commands: Commands) {
+            commands.add_resource(q.fetch().len());
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Health(10),))
+            .create_entity((Health(10), Poisoned))
+            .add_systems(SystemSchedule::Update, (poison, count_changed));
+
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 2);
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 1);
+    }
+
+    #[test]
+    fn mutable_fetch_marks_only_what_is_written_as_changed() {
+        use crate::core::{command::Commands, system::SystemSchedule, world::World};
+
+        struct Health(i32);
+
+        fn heal_wounded(mut q: Query<(&mut Health,)>) {
+            for mut health in q.iter_mut() {
+                if health.0 < 10 {
+                    health.0 += 1;
+                }
+            }
+        }
+        fn count_changed(q: Query<(&Health,), Changed<Health>>, mut commands: Commands) {
+            commands.add_resource(q.fetch().len());
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Health(10),))
+            .create_entity((Health(5),))
+            .add_systems(SystemSchedule::Update, (heal_wounded, count_changed));
+
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 2);
+        world.run_update();
+        assert_eq!(*world.get_resource::<usize>().unwrap(), 1);
+    }
+
+    #[test]
+    fn optional_fetch_matches_entities_with_and_without_the_component() {
+        #[derive(Debug, PartialEq)]
+        struct Position(i32);
+        #[derive(Debug, PartialEq)]
+        struct Sprite(&'static str);
+
+        let mut em = EntityManager::new();
+        em.create_entity((Position(1),));
+        em.create_entity((Position(2), Sprite("tree")));
+        em.create_entity((Sprite("cloud"),));
+
+        let mut results = Query::<(&Position, Option<&Sprite>)>::new(&em).fetch();
+        results.sort_by_key(|(position, _)| position.0);
+        assert_eq!(
+            results,
+            vec![(&Position(1), None), (&Position(2), Some(&Sprite("tree")))]
+        );
+
+        for mut sprite in Query::<Option<&mut Sprite>>::new_mut(&mut em)
+            .into_iter()
+            .flatten()
+        {
+            sprite.0 = "rock";
+        }
+        let sprites = Query::<&Sprite>::new(&em).fetch();
+        assert!(sprites.iter().all(|sprite| sprite.0 == "rock"));
+        assert_eq!(sprites.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "SystemParam conflict")]
+    fn optional_fetch_does_not_prove_queries_disjoint() {
+        use crate::core::{system::SystemSchedule, world::World};
+
+        #[allow(dead_code)]
+        struct Position(i32);
+        struct Hidden;
+
+        // `Option<&Hidden>` still matches entities without `Hidden`, so both queries can reach
+        // the same `Position`.
+        fn system(
+            _: Query<(&mut Position,), Without<&Hidden>>,
+            _: Query<(&Position, Option<&Hidden>)>,
+        ) {
+        }
+
+        let mut world = World::new();
+        world
+            .create_entity((Position(0),))
+            .add_system(SystemSchedule::Update, system);
+        world.run_update();
+    }
+
+    #[test]
+    fn query_iter_is_lazy_and_knows_its_exact_length() {
+        #[derive(Debug, PartialEq)]
+        struct Health(i32);
+        #[allow(dead_code)]
+        struct Name(&'static str);
+
+        let mut em = EntityManager::new();
+        em.create_entity((Health(1),));
+        em.create_entity((Health(2), Name("b")));
+        em.create_entity((Health(3),));
+        em.create_entity((Name("d"),));
+
+        let mut q = Query::<&mut Health>::new_mut(&mut em);
+        let mut iter = q.iter_mut();
+        assert_eq!(iter.len(), 3);
+        iter.next().unwrap().0 += 10;
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        for mut health in iter {
+            health.0 += 10;
+        }
+
+        let q = Query::<(&Health,), Without<&Name>>::new(&em);
+        assert_eq!(q.iter().len(), 2);
+        let mut total = 0;
+        for health in &q {
+            total += health.0;
+        }
+        assert_eq!(total, 11 + 13);
+        assert_eq!(Query::<&Name>::new(&em).iter().count(), 2);
+    }
+
+    #[test]
+    fn row_filtered_query_iter_only_bounds_its_length() {
+        struct Health(i32);
+
+        let mut em = EntityManager::new();
+        let first = em.create_entity((Health(1),));
+        em.create_entity((Health(2),));
+        em.change_tick += 1;
+        em.get_component_mut::<Health>(first).unwrap().0 = 5;
+
+        let q = Query::<(&Health,), Changed<Health>>::new_with_cache(
+            &em,
+            SystemTicks {
+                last_run: 1,
+                this_run: em.change_tick,
+            },
+        );
+        let iter = q.iter();
+        assert_eq!(iter.size_hint(), (0, Some(2)));
+        let changed = iter.map(|health| health.0).collect::<Vec<_>>();
+        assert_eq!(changed, vec![5]);
+    }
+
+    #[test]
+    fn get_reads_one_entity_and_respects_the_constraint() {
+        #[derive(Debug, PartialEq)]
+        struct Health(i32);
+        struct Dead;
+
+        let mut em = EntityManager::new();
+        let alive = em.create_entity((Health(3),));
+        let dead = em.create_entity((Health(0), Dead));
+        let removed = em.create_entity((Health(9),));
+        em.remove_entity(removed).unwrap();
+
+        let q = Query::<&Health, Without<&Dead>>::new(&em);
+        assert_eq!(q.get(alive).unwrap(), &Health(3));
+        assert!(matches!(q.get(dead), Err(QueryError::QueryDoesNotMatch(_))));
+        assert!(matches!(q.get(removed), Err(QueryError::StaleEntity(_))));
+
+        let mut q = Query::<&mut Health>::new_mut(&mut em);
+        q.get_mut(dead).unwrap().0 = 1;
+        assert_eq!(Query::<&Health>::new(&em).get(dead).unwrap(), &Health(1));
+    }
+
+    #[test]
+    fn get_many_mut_rejects_duplicate_entities() {
+        #[derive(Debug, PartialEq)]
+        struct Position(i32);
+
+        let mut em = EntityManager::new();
+        let follower = em.create_entity((Position(0),));
+        let target = em.create_entity((Position(10),));
+
+        let mut q = Query::<&mut Position>::new_mut(&mut em);
+        assert!(matches!(
+            q.get_many_mut([follower, follower]),
+            Err(QueryError::DuplicateEntity(_))
+        ));
+        let [mut follower_position, target_position] =
+            q.get_many_mut([follower, target]).unwrap();
+        follower_position.0 = target_position.0 - 1;
+
+        let q = Query::<&Position>::new(&em);
+        assert_eq!(
+            q.get_many([follower, target]).unwrap(),
+            [&Position(9), &Position(10)]
+        );
+    }
+
+    #[test]
+    fn single_distinguishes_no_entities_from_multiple_entities() {
+        use crate::core::{system::SystemSchedule, world::World};
+
+        #[derive(Debug, PartialEq)]
+        struct Camera(i32);
+        struct Enemy;
+
+        let mut em = EntityManager::new();
+        em.create_entity((Enemy,));
+        em.create_entity((Enemy,));
+        assert!(matches!(
+            Query::<&Camera>::new(&em).get_single(),
+            Err(QuerySingleError::NoEntities(_))
+        ));
+        assert!(matches!(
+            Query::<&Enemy>::new(&em).get_single(),
+            Err(QuerySingleError::MultipleEntities(_))
+        ));
+
+        // Through the query cache, as a system parameter.
+        fn zoom(mut q: Query<&mut Camera>) {
+            q.single_mut().0 += 1;
+        }
+        let mut world = World::new();
+        world
+            .create_entity((Camera(1),))
+            .create_entity((Enemy,))
+            .add_system(SystemSchedule::Update, zoom);
+        world.run_update();
+        world.run_update();
+        assert_eq!(world.create_query::<&Camera>().single(), &Camera(3));
+
+        world.create_query_mut::<&mut Camera>().single_mut().0 = 10;
+        assert_eq!(world.create_query::<&Camera>().single(), &Camera(10));
+    }
 }
@@ -1,58 +1,83 @@
 use std::{
     any::TypeId,
-    cell::RefCell,
     collections::HashMap,
+    mem::ManuallyDrop,
     ops::{Deref, DerefMut},
-    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
 };
 
-use super::{access::AccessKey, as_any_trait::AsAny, coordinator::Coordinator, system::SystemParam};
+use super::{
+    access::{AccessKey, SystemAccess},
+    as_any_trait::AsAny,
+    coordinator::Coordinator,
+    system::{SystemMeta, SystemParam},
+    world::{FromWorld, World},
+};
 
-/// Object-safe marker implemented by every [`Resource<T>`], letting [`ResourceManager`] store
+/// Object-safe marker implemented by every [`ResMut<T>`], letting [`ResourceManager`] store
 /// resources of different types in a single `HashMap` and downcast them back via [`AsAny`].
-pub trait ResourceTrait: AsAny {}
+pub trait ResourceTrait: AsAny + Send + Sync {}
 
 /// A shared, reference-counted handle to a single global value of type `T`, dereferencing to
 /// `&T`/`&mut T`. Obtained via [`World::get_resource`](super::world::World::get_resource) or
-/// as a system parameter.
-pub struct Resource<T: ?Sized> {
+/// as a system parameter, declared as writing `T`; take [`Res<T>`] instead in systems that
+/// only read it, so they can run in parallel.
+pub struct ResMut<T: ?Sized> {
     value: *const T,
     pub type_id: std::any::TypeId,
-    counter: Rc<RefCell<u32>>,
+    counter: Arc<AtomicU32>,
 }
 
-impl<T: 'static> ResourceTrait for Resource<T> {}
+/// The name [`ResMut`] had before [`Res`] was added, kept for compatibility.
+pub type Resource<T> = ResMut<T>;
+
+// Handles only ever hand out the value to systems whose accesses were checked not to conflict.
+unsafe impl<T: ?Sized + Send + Sync> Send for ResMut<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for ResMut<T> {}
+
+impl<T: 'static + Send + Sync> ResourceTrait for ResMut<T> {}
 
-impl<T: 'static> Resource<T> {
+impl<T: 'static> ResMut<T> {
     /// Wraps `value` as a resource. Usually not called directly — prefer
     /// [`World::add_resource`](super::world::World::add_resource).
     pub fn new(value: T) -> Self {
         let ptr = Box::into_raw(Box::new(value));
-        Resource {
+        ResMut {
             value: ptr,
             type_id: std::any::TypeId::of::<T>(),
-            counter: Rc::new(RefCell::new(1)),
+            counter: Arc::new(AtomicU32::new(1)),
+        }
+    }
+
+    /// Takes the value out of the only handle to it.
+    fn into_inner(self) -> T {
+        assert_eq!(self.counter.load(Ordering::Acquire), 1);
+        let handle = ManuallyDrop::new(self);
+        unsafe {
+            drop(std::ptr::read(&handle.counter));
+            *Box::from_raw(handle.value as *mut T)
         }
     }
 }
 
-impl<T: 'static> Clone for Resource<T> {
+impl<T: 'static> Clone for ResMut<T> {
     fn clone(&self) -> Self {
-        let counter = self.counter.clone();
-        *counter.borrow_mut() += 1;
-        Resource {
+        self.counter.fetch_add(1, Ordering::Relaxed);
+        ResMut {
             value: self.value,
             type_id: self.type_id,
-            counter,
+            counter: self.counter.clone(),
         }
     }
 }
 
-impl<T: ?Sized> Drop for Resource<T> {
+impl<T: ?Sized> Drop for ResMut<T> {
     fn drop(&mut self) {
         unsafe {
-            if *self.counter.borrow() > 1 {
-                *self.counter.borrow_mut() -= 1;
+            if self.counter.fetch_sub(1, Ordering::AcqRel) > 1 {
                 return;
             }
             let value = self.value as *mut T;
@@ -61,7 +86,7 @@ impl<T: ?Sized> Drop for Resource<T> {
     }
 }
 
-impl<T: 'static> Deref for Resource<T> {
+impl<T: 'static> Deref for ResMut<T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -69,13 +94,13 @@ impl<T: 'static> Deref for Resource<T> {
     }
 }
 
-impl<T: 'static> DerefMut for Resource<T> {
+impl<T: 'static> DerefMut for ResMut<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { &mut *(self.value as *mut T) }
     }
 }
 
-impl<T: 'static> AsAny for Resource<T> {
+impl<T: 'static> AsAny for ResMut<T> {
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -85,31 +110,106 @@ impl<T: 'static> AsAny for Resource<T> {
     }
 }
 
-impl<T: 'static> std::fmt::Display for Resource<T> {
+impl<T: 'static> std::fmt::Display for ResMut<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Resource<{}>", std::any::type_name::<T>())
     }
 }
 
-impl<T: 'static> SystemParam for Resource<T> {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        // Always tracked as mutable: `Resource<T>` always exposes `DerefMut`, so a second
-        // handle to the same `T` in this system could alias a live `&mut T` from this one.
-        coordinator.borrow().access_tracker.borrow_mut().track(
+impl<T: 'static> SystemParam for ResMut<T> {
+    fn access(access: &mut SystemAccess) {
+        // Always declared mutable: `ResMut<T>` always exposes `DerefMut`, so a second
+        // handle to the same `T` could alias a live `&mut T` from this one.
+        access.add(
             AccessKey::Resource(TypeId::of::<T>()),
             true,
             std::any::type_name::<T>(),
         );
-        let resource_manager = coordinator.borrow().resources.clone();
-        let resource = resource_manager.borrow().get_resource::<T>().unwrap();
-        resource.clone()
+    }
+
+    fn get_param(coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        coordinator
+            .resources
+            .borrow()
+            .fetch::<T>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "system '{}' requires the resource {}, which hasn't been added; add it \
+                     first, or take an Option<Res<_>>/Option<ResMut<_>> instead",
+                    meta.name,
+                    std::any::type_name::<T>()
+                )
+            })
+    }
+}
+
+/// `None` while the resource of type `T` hasn't been added, e.g. by an optional extension.
+impl<T: 'static> SystemParam for Option<ResMut<T>> {
+    fn access(access: &mut SystemAccess) {
+        ResMut::<T>::access(access);
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        coordinator.resources.borrow().fetch::<T>()
+    }
+}
+
+/// A [`SystemParam`] giving read-only access to the resource of type `T`, dereferencing to
+/// `&T` only. Unlike [`ResMut<T>`], it's declared as a read, so systems reading the same
+/// resource through `Res` can run in parallel, and it can be used by run conditions.
+///
+/// ```
+/// # use dark_iron_ecs::core::resources::{Res, ResMut};
+/// struct Gravity(f32);
+/// struct Score(u32);
+///
+/// fn fall(gravity: Res<Gravity>, mut score: ResMut<Score>) {
+///     score.0 += gravity.0 as u32;
+/// }
+/// ```
+pub struct Res<T: 'static> {
+    resource: ResMut<T>,
+}
+
+impl<T: 'static> Deref for Res<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.resource
     }
 }
 
-/// Owns every registered global [`Resource`], keyed by type. Most callers interact with it
-/// indirectly through [`World`](super::world::World) rather than directly.
+impl<T: 'static> SystemParam for Res<T> {
+    fn access(access: &mut SystemAccess) {
+        access.add(
+            AccessKey::Resource(TypeId::of::<T>()),
+            false,
+            std::any::type_name::<T>(),
+        );
+    }
+
+    fn get_param(coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        Res {
+            resource: ResMut::get_param(coordinator, meta),
+        }
+    }
+}
+
+/// `None` while the resource of type `T` hasn't been added, e.g. by an optional extension.
+impl<T: 'static> SystemParam for Option<Res<T>> {
+    fn access(access: &mut SystemAccess) {
+        Res::<T>::access(access);
+    }
+
+    fn get_param(coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        Option::<ResMut<T>>::get_param(coordinator, meta).map(|resource| Res { resource })
+    }
+}
+
+/// Owns every registered global [`ResMut`], keyed by type. Most callers interact with it
+/// indirectly through [`World`] rather than directly.
 pub struct ResourceManager {
-    pub resources: HashMap<TypeId, Rc<dyn ResourceTrait>>,
+    pub(crate) resources: HashMap<TypeId, Arc<dyn ResourceTrait>>,
 }
 
 impl ResourceManager {
@@ -121,38 +221,90 @@ impl ResourceManager {
     }
 
     /// Inserts `resource`, replacing any existing resource of the same type.
-    pub fn add<T: 'static>(&mut self, resource: T) {
-        let res = Resource::new(resource);
-        self.resources.insert(TypeId::of::<T>(), Rc::new(res));
+    pub fn add<T: 'static + Send + Sync>(&mut self, resource: T) {
+        let res = ResMut::new(resource);
+        self.resources.insert(TypeId::of::<T>(), Arc::new(res));
+    }
+
+    /// Returns a read-only handle to the resource of type `T`, or `None` if it hasn't been
+    /// added.
+    pub fn get_resource<T: 'static>(&self) -> Option<Res<T>> {
+        self.fetch().map(|resource| Res { resource })
     }
 
     /// Returns a handle to the resource of type `T`, or `None` if it hasn't been added.
-    pub fn get_resource<T: 'static>(&self) -> Option<Resource<T>> {
+    pub fn get_resource_mut<T: 'static>(&mut self) -> Option<ResMut<T>> {
+        self.fetch()
+    }
+
+    /// Returns a mutable handle to the resource of type `T` through a shared reference. Only
+    /// for callers that were checked to be the only ones accessing it.
+    pub(crate) fn fetch<T: 'static>(&self) -> Option<ResMut<T>> {
         let resource = self.resources.get(&TypeId::of::<T>())?;
-        let resource = resource.as_any().downcast_ref::<Resource<T>>()?;
+        let resource = resource.as_any().downcast_ref::<ResMut<T>>()?;
         Some(resource.clone())
     }
+
+    /// Returns whether a resource of type `T` has been added.
+    pub fn contains_resource<T: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Adds the resource of type `T` built via [`FromWorld`] from `world` (e.g. `T::default()`),
+    /// unless one was already added. To initialize a resource of `world` itself, call
+    /// [`World::init_resource`] instead.
+    pub fn init_resource<T: FromWorld + Send + Sync + 'static>(&mut self, world: &mut World) {
+        if !self.contains_resource::<T>() {
+            let resource = T::from_world(world);
+            self.add(resource);
+        }
+    }
+
+    /// Removes the resource of type `T` and returns it, or `None` if it hasn't been added.
+    ///
+    /// # Panics
+    /// Panics, leaving the resource in place, if another handle to it is still alive.
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        let resource = self.fetch::<T>()?;
+        // Only the manager's own handle, and the one just taken.
+        if resource.counter.load(Ordering::Acquire) != 2 {
+            panic!(
+                "cannot remove resource {}: a handle to it is still alive",
+                std::any::type_name::<T>()
+            );
+        }
+        self.resources.remove(&TypeId::of::<T>());
+        Some(resource.into_inner())
+    }
 }
 
+/// Reaches every resource, so it counts as reading all of them: it conflicts with any
+/// [`ResMut`] or `&mut ResourceManager`, and only hands out [`Res`] handles.
 impl SystemParam for &ResourceManager {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(TypeId::of::<ResourceManager>()),
             false,
             "ResourceManager",
         );
-        unsafe { &(*coordinator.borrow().get_resource_manager_mut()) }
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &(*coordinator.get_resource_manager_mut()) }
     }
 }
 
 impl SystemParam for &mut ResourceManager {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        coordinator.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        access.add(
             AccessKey::Manager(TypeId::of::<ResourceManager>()),
             true,
             "ResourceManager",
         );
-        unsafe { &mut (*coordinator.borrow().get_resource_manager_mut()) }
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &mut (*coordinator.get_resource_manager_mut()) }
     }
 }
 
@@ -161,3 +313,150 @@ impl Default for ResourceManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod resources_test {
+    use super::{Res, ResMut, ResourceManager};
+    use crate::core::{
+        system::{IntoSystemConfig, System, SystemSchedule},
+        world::{FromWorld, World},
+    };
+
+    struct Config(u32);
+
+    fn read(config: Res<Config>, again: Res<Config>) {
+        assert_eq!(config.0, again.0);
+    }
+
+    fn write(mut config: ResMut<Config>) {
+        config.0 += 1;
+    }
+
+    #[test]
+    fn readers_never_conflict_but_writers_do() {
+        let reads = System::access(&read);
+        let writes = System::access(&write);
+        assert!(reads.conflict().is_none());
+        assert!(reads.is_read_only());
+        assert!(!reads.conflicts_with(&reads));
+        assert!(reads.conflicts_with(&writes));
+        assert!(writes.conflicts_with(&writes));
+    }
+
+    #[test]
+    fn the_shared_resource_manager_reads_every_resource() {
+        fn inspect(resources: &ResourceManager) {
+            assert!(resources.get_resource::<Config>().is_some());
+        }
+
+        let inspects = System::access(&inspect);
+        assert!(inspects.is_read_only());
+        assert!(!inspects.conflicts_with(&System::access(&read)));
+        assert!(inspects.conflicts_with(&System::access(&write)));
+        let both = System::access(&(|_: &ResourceManager, _: ResMut<Config>| {}));
+        assert!(both.conflict().is_some());
+    }
+
+    #[test]
+    fn optional_resources_are_none_until_added() {
+        fn count(config: Option<Res<Config>>, mut seen: ResMut<Vec<bool>>) {
+            seen.push(config.is_some());
+        }
+
+        let mut world = World::new();
+        world
+            .add_resource(Vec::<bool>::new())
+            .add_system(SystemSchedule::Update, count);
+
+        world.run_update();
+        world.add_resource(Config(0));
+        world.run_update();
+        assert_eq!(*world.get_resource::<Vec<bool>>().unwrap(), [false, true]);
+    }
+
+    #[test]
+    #[should_panic(expected = "system 'dark_iron_ecs::core::resources::resources_test::write' \
+                               requires the resource dark_iron_ecs::core::resources::\
+                               resources_test::Config")]
+    fn missing_resource_panic_names_the_system_and_type() {
+        World::new()
+            .add_system(SystemSchedule::Update, write)
+            .run_update();
+    }
+
+    #[test]
+    fn readers_see_writes_of_earlier_systems() {
+        let mut world = World::new();
+        world.add_resource(Config(1)).add_systems(
+            SystemSchedule::Update,
+            (read, write.label("write"), read.after("write")),
+        );
+
+        world.run_update();
+        assert_eq!(world.get_resource::<Config>().unwrap().0, 2);
+    }
+
+    #[test]
+    fn resources_can_be_initialized_removed_and_scoped() {
+        #[derive(Default)]
+        struct Level(u32);
+
+        struct Spawner(u32);
+
+        impl FromWorld for Spawner {
+            fn from_world(world: &mut World) -> Self {
+                Spawner(world.get_resource::<Level>().unwrap().0 * 10)
+            }
+        }
+
+        let mut world = World::new();
+        world.init_resource::<Level>();
+        world.get_resource::<Level>().unwrap().0 = 2;
+        world.init_resource::<Level>().init_resource::<Spawner>();
+        assert_eq!(world.get_resource::<Spawner>().unwrap().0, 20);
+
+        let spawned = world.resource_scope(|world, spawner: &mut Spawner| {
+            spawner.0 += 1;
+            world.get_resource::<Level>().unwrap().0 += 1;
+            spawner.0
+        });
+        assert_eq!(spawned, 21);
+        assert_eq!(world.get_resource::<Level>().unwrap().0, 3);
+
+        assert_eq!(world.remove_resource::<Spawner>().map(|s| s.0), Some(21));
+        assert!(!world.contains_resource::<Spawner>());
+        assert!(world.remove_resource::<Spawner>().is_none());
+        assert!(world.contains_resource::<Level>());
+
+        let mut resources = ResourceManager::new();
+        resources.init_resource::<Spawner>(&mut world);
+        world.get_resource::<Level>().unwrap().0 = 5;
+        resources.init_resource::<Spawner>(&mut world);
+        assert_eq!(resources.get_resource::<Spawner>().unwrap().0, 30);
+    }
+
+    #[test]
+    fn resource_scope_puts_the_resource_back_when_it_panics() {
+        let mut world = World::new();
+        world.add_resource(Config(1));
+
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.resource_scope(|_, config: &mut Config| {
+                config.0 += 1;
+                panic!("scope failed");
+            })
+        }));
+
+        assert!(outcome.is_err());
+        assert_eq!(world.get_resource::<Config>().unwrap().0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "a handle to it is still alive")]
+    fn removing_a_resource_still_in_use_panics() {
+        let mut world = World::new();
+        world.add_resource(Config(0));
+        let _config = world.get_resource::<Config>();
+        world.remove_resource::<Config>();
+    }
+}
@@ -0,0 +1,551 @@
+use std::{cmp::Reverse, collections::BinaryHeap, num::NonZero, sync::Mutex};
+
+use super::{
+    command::CommandQueue,
+    condition::BoxedCondition,
+    coordinator::Coordinator,
+    error::SystemError,
+    error::SystemFailure,
+    error_handler::ErrorHandler,
+    label::{SystemLabel, define_label},
+    system::{BoxedSystem, SystemConfig, SystemResult},
+    world::World,
+};
+
+define_label!(
+    /// Names a [`Schedule`]: one of the built-in
+    /// [`SystemSchedule`](super::system::SystemSchedule)s, or any other
+    /// `Debug + Clone + Eq + Hash` type, such as your own `Render` or `Physics` struct or enum.
+    /// Run a schedule by its label via [`World::run_schedule`], or make it one of the stages
+    /// [`World::run_update`] runs via [`World::add_stage_after`].
+    ///
+    /// [`World::run_schedule`]: super::world::World::run_schedule
+    /// [`World::run_update`]: super::world::World::run_update
+    /// [`World::add_stage_after`]: super::world::World::add_stage_after
+    ScheduleLabel
+);
+
+/// One system of a [`Schedule`], with its ordering constraints.
+struct ScheduledSystem {
+    system: BoxedSystem,
+    conditions: Vec<BoxedCondition>,
+    labels: Vec<Box<dyn SystemLabel>>,
+    before: Vec<Box<dyn SystemLabel>>,
+    after: Vec<Box<dyn SystemLabel>>,
+    /// Systems (by index) this one was chained after.
+    after_systems: Vec<usize>,
+}
+
+/// The systems registered under one [`ScheduleLabel`], and the order they run in.
+///
+/// A system runs after every system it's ordered after (via labels, or by being chained), and
+/// unconstrained systems keep their registration order. Consecutive systems in that order whose
+/// accesses don't conflict, and which aren't ordered relative to each other, form a batch and
+/// run in parallel.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<ScheduledSystem>,
+    /// Indices into `systems`, in run order.
+    order: Vec<usize>,
+    /// For every system, the systems it must run after.
+    dependencies: Vec<Vec<usize>>,
+}
+
+impl Schedule {
+    /// Returns how many systems are registered.
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Returns whether no system is registered.
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Iterates the names of the registered systems, in the order they run.
+    pub fn system_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.order
+            .iter()
+            .map(|&index| self.systems[index].system.name())
+    }
+
+    /// Adds `configs` (each run after the previous one if `chained`) and re-sorts the
+    /// schedule. Returns an error, leaving the schedule unchanged, if one of the systems'
+    /// parameters conflict or the new constraints form a cycle.
+    pub(crate) fn add(
+        &mut self,
+        configs: Vec<SystemConfig>,
+        chained: bool,
+    ) -> Result<(), SystemError> {
+        for config in &configs {
+            if let Some(conflict) = config.system.access().conflict() {
+                return Err(SystemError::ConflictingAccess {
+                    system: config.system.name(),
+                    conflict: conflict.to_owned(),
+                });
+            }
+            if let Some((_, input)) = config.system.access().input() {
+                return Err(SystemError::UnpipedInput {
+                    system: config.system.name(),
+                    input,
+                });
+            }
+            for condition in &config.conditions {
+                condition.validate(config.system.name())?;
+            }
+        }
+
+        let first = self.systems.len();
+        for (offset, config) in configs.into_iter().enumerate() {
+            let after_systems = if chained && offset > 0 {
+                vec![first + offset - 1]
+            } else {
+                Vec::new()
+            };
+            self.systems.push(ScheduledSystem {
+                system: config.system,
+                conditions: config.conditions,
+                labels: config.labels,
+                before: config.before,
+                after: config.after,
+                after_systems,
+            });
+        }
+
+        self.resort(first)
+    }
+
+    /// Adds every system of `other`, keeping their ordering constraints, and re-sorts the
+    /// schedule. Returns an error, leaving the schedule unchanged, if the combined constraints
+    /// form a cycle.
+    pub(crate) fn append(&mut self, other: Schedule) -> Result<(), SystemError> {
+        let first = self.systems.len();
+        self.systems
+            .extend(other.systems.into_iter().map(|mut scheduled| {
+                for index in scheduled.after_systems.iter_mut() {
+                    *index += first;
+                }
+                scheduled
+            }));
+        self.resort(first)
+    }
+
+    /// Clamps the change ticks of every system and run condition to at most
+    /// [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before `change_tick`.
+    pub(crate) fn clamp_change_ticks(&mut self, change_tick: u32) {
+        for scheduled in &mut self.systems {
+            scheduled.system.meta.clamp_ticks(change_tick);
+            for condition in &mut scheduled.conditions {
+                condition.clamp_ticks(change_tick);
+            }
+        }
+    }
+
+    /// Sorts the systems again after some were pushed from index `first` on, removing them
+    /// again if that creates a cycle.
+    fn resort(&mut self, first: usize) -> Result<(), SystemError> {
+        match self.sort() {
+            Ok((order, dependencies)) => {
+                self.order = order;
+                self.dependencies = dependencies;
+                Ok(())
+            }
+            Err(error) => {
+                self.systems.truncate(first);
+                Err(error)
+            }
+        }
+    }
+
+    /// Topologically sorts the systems, always running the earliest-registered system whose
+    /// dependencies are done next. Returns the run order and each system's dependencies, or
+    /// [`SystemError::OrderingCycle`] naming the systems of a cycle.
+    fn sort(&self) -> Result<(Vec<usize>, Vec<Vec<usize>>), SystemError> {
+        let count = self.systems.len();
+        let mut dependencies: Vec<Vec<usize>> = self
+            .systems
+            .iter()
+            .map(|system| system.after_systems.clone())
+            .collect();
+        for (index, system) in self.systems.iter().enumerate() {
+            for (other, other_system) in self.systems.iter().enumerate() {
+                let labeled = |labels: &[Box<dyn SystemLabel>]| {
+                    labels
+                        .iter()
+                        .any(|label| other_system.labels.contains(label))
+                };
+                if labeled(&system.after) {
+                    dependencies[index].push(other);
+                }
+                if labeled(&system.before) {
+                    dependencies[other].push(index);
+                }
+            }
+        }
+        for dependencies in dependencies.iter_mut() {
+            dependencies.sort_unstable();
+            dependencies.dedup();
+        }
+
+        let mut dependents = vec![Vec::new(); count];
+        let mut pending: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        for (index, dependencies) in dependencies.iter().enumerate() {
+            for &dependency in dependencies {
+                dependents[dependency].push(index);
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..count)
+            .filter(|&index| pending[index] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for &dependent in &dependents[index] {
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        if order.len() < count {
+            return Err(SystemError::OrderingCycle(
+                self.find_cycle(&dependencies, &pending),
+            ));
+        }
+        Ok((order, dependencies))
+    }
+
+    /// Finds a cycle among the systems left unsorted (`pending` dependencies > 0), returning
+    /// their names in run-order direction.
+    fn find_cycle(&self, dependencies: &[Vec<usize>], pending: &[usize]) -> Vec<&'static str> {
+        // Every unsorted system has an unsorted dependency, so walking from one to the next
+        // must eventually revisit a system.
+        let mut path = vec![pending.iter().position(|&count| count > 0).unwrap()];
+        loop {
+            let current = *path.last().unwrap();
+            let next = dependencies[current]
+                .iter()
+                .copied()
+                .find(|&dependency| pending[dependency] > 0)
+                .unwrap();
+            if let Some(start) = path.iter().position(|&index| index == next) {
+                let mut cycle: Vec<usize> = path[start..].iter().rev().copied().collect();
+                // Start from the earliest-registered system, so the report is deterministic.
+                let earliest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+                cycle.rotate_left(earliest);
+                return cycle
+                    .into_iter()
+                    .map(|index| self.systems[index].system.name())
+                    .collect();
+            }
+            path.push(next);
+        }
+    }
+
+    /// Returns the end of the batch starting at position `start` of the run order: the first
+    /// system that conflicts with, or is ordered after, a system already in the batch.
+    fn batch_end(&self, start: usize) -> usize {
+        let mut end = start + 1;
+        while end < self.order.len() {
+            let candidate = self.order[end];
+            let access = self.systems[candidate].system.access();
+            if self.order[start..end].iter().any(|&index| {
+                self.dependencies[candidate].contains(&index)
+                    || self.systems[index].system.access().conflicts_with(access)
+            }) {
+                break;
+            }
+            end += 1;
+        }
+        end
+    }
+
+    /// Runs every system whose conditions hold once, batch by batch. Each batch runs in
+    /// parallel; once it finishes, the [`Commands`](super::command::Commands) its systems
+    /// recorded are applied in run order, so later systems see their effects, and the errors
+    /// they returned are passed to `error_handler`, also in run order, after those of their
+    /// failed commands.
+    pub(crate) fn run(
+        &mut self,
+        world: &World,
+        coordinator: &Coordinator,
+        error_handler: &ErrorHandler,
+    ) {
+        let mut start = 0;
+        while start < self.order.len() {
+            let end = self.batch_end(start);
+
+            // Conditions run here, one after the other, so they can't overlap the batch.
+            let mut change_tick = world.entity_manager.borrow().change_tick;
+            let mut runnable = Vec::with_capacity(end - start);
+            for &index in &self.order[start..end] {
+                let scheduled = &mut self.systems[index];
+                if scheduled
+                    .conditions
+                    .iter_mut()
+                    .all(|condition| condition.evaluate(coordinator, &mut change_tick))
+                {
+                    scheduled.system.meta.begin_run(&mut change_tick);
+                    runnable.push(index);
+                }
+            }
+            world.entity_manager.borrow_mut().change_tick = change_tick;
+
+            let batch: Vec<&BoxedSystem> = runnable
+                .iter()
+                .map(|&index| &self.systems[index].system)
+                .collect();
+            let results = run_batch(&batch, coordinator);
+            for (system, result) in batch.into_iter().zip(results) {
+                let fail = |error| {
+                    error_handler(
+                        world,
+                        SystemFailure {
+                            system: system.name(),
+                            error,
+                        },
+                    )
+                };
+                CommandQueue::apply(&system.meta.command_queue, world, |error| {
+                    fail(Box::new(error))
+                });
+                if let Err(error) = result {
+                    fail(error);
+                }
+            }
+
+            start = end;
+        }
+    }
+}
+
+/// Runs a batch of mutually non-conflicting systems, spreading them over up to one worker
+/// thread per core, and returns what each system returned, in batch order. A panic in any
+/// system is resumed on the calling thread once the batch is done.
+fn run_batch(batch: &[&BoxedSystem], coordinator: &Coordinator) -> Vec<SystemResult> {
+    if let [system] = batch {
+        return vec![system.run(coordinator)];
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .min(batch.len());
+    let pending = Mutex::new(batch.iter().enumerate());
+    let mut results: Vec<(usize, SystemResult)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let next = pending.lock().unwrap().next();
+                        let Some((index, system)) = next else { break };
+                        results.push((index, system.run(coordinator)));
+                    }
+                    results
+                })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(batch.len());
+        for handle in handles {
+            match handle.join() {
+                Ok(worker_results) => results.extend(worker_results),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        results
+    });
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod schedule_test {
+    use crate::core::{
+        command::Commands,
+        error::SystemError,
+        query::Query,
+        resources::Resource,
+        system::{IntoSystemConfig, SystemBundle, SystemManager, SystemSchedule},
+        world::World,
+    };
+
+    struct Position(i32);
+    struct Velocity(i32);
+
+    fn move_positions(mut q: Query<(&mut Position,)>) {
+        for mut position in q.iter_mut() {
+            position.0 += 1;
+        }
+    }
+
+    fn slow_down(mut q: Query<(&mut Velocity,)>) {
+        for mut velocity in q.iter_mut() {
+            velocity.0 -= 1;
+        }
+    }
+
+    fn report(q: Query<(&Position, &Velocity)>, mut commands: Commands) {
+        let sum: i32 = q.iter().map(|(p, v)| p.0 + v.0).sum();
+        commands.add_resource(sum);
+    }
+
+    fn read_sum(sum: Resource<i32>) {
+        assert!(*sum >= 0);
+    }
+
+    #[test]
+    fn consecutive_non_conflicting_systems_share_a_batch() {
+        let mut manager = SystemManager::new();
+        manager.add_system(SystemSchedule::Update, move_positions);
+        manager.add_system(SystemSchedule::Update, slow_down);
+        manager.add_system(SystemSchedule::Update, report);
+        manager.add_system(SystemSchedule::Update, read_sum);
+
+        let schedule = manager.schedule(SystemSchedule::Update).unwrap();
+        // `report` reads what both writers write; `read_sum` reads a resource `report`'s
+        // commands may replace.
+        assert_eq!(schedule.batch_end(0), 2);
+        assert_eq!(schedule.batch_end(2), 3);
+        assert_eq!(schedule.batch_end(3), 4);
+
+        // Ordered systems never share a batch, even when their accesses don't conflict.
+        let mut manager = SystemManager::new();
+        manager.add_systems(SystemSchedule::Update, (move_positions, slow_down).chain());
+        assert_eq!(
+            manager
+                .schedule(SystemSchedule::Update)
+                .unwrap()
+                .batch_end(0),
+            1
+        );
+    }
+
+    #[test]
+    fn systems_run_in_parallel_still_see_earlier_batches() {
+        let mut world = World::new();
+        world
+            .create_entity((Position(0), Velocity(10)))
+            .create_entity((Position(5), Velocity(3)))
+            .add_systems(SystemSchedule::Update, (move_positions, slow_down, report));
+
+        world.run_update();
+        assert_eq!(*world.get_resource::<i32>().unwrap(), 18);
+        world.run_update();
+        assert_eq!(*world.get_resource::<i32>().unwrap(), 18);
+    }
+
+    type Log = Vec<&'static str>;
+
+    fn input(mut log: Resource<Log>) {
+        log.push("input");
+    }
+
+    fn physics(mut log: Resource<Log>) {
+        log.push("physics");
+    }
+
+    fn render(mut log: Resource<Log>) {
+        log.push("render");
+    }
+
+    fn audio(mut log: Resource<Log>) {
+        log.push("audio");
+    }
+
+    fn run_log(world: &mut World) -> Log {
+        world.add_resource(Log::new());
+        world.run_update();
+        world.get_resource::<Log>().unwrap().to_vec()
+    }
+
+    #[test]
+    fn before_and_after_override_registration_order() {
+        let mut world = World::new();
+        world
+            .add_system(SystemSchedule::Update, render.label("render"))
+            .add_system(SystemSchedule::Update, audio)
+            .add_system(
+                SystemSchedule::Update,
+                physics.label("physics").before("render"),
+            )
+            .add_system(SystemSchedule::Update, input.before("physics"));
+
+        // `audio` is unconstrained and registered before any other system that's ready.
+        assert_eq!(run_log(&mut world), ["audio", "input", "physics", "render"]);
+    }
+
+    #[test]
+    fn chained_systems_run_in_listed_order() {
+        let mut world = World::new();
+        world.add_systems(SystemSchedule::Update, (render, physics, input).chain());
+        assert_eq!(run_log(&mut world), ["render", "physics", "input"]);
+
+        let mut world = World::new();
+        world
+            .add_system(SystemSchedule::Update, audio.after("stages"))
+            .add_systems(
+                SystemSchedule::Update,
+                (input, physics, render).chain().label("stages"),
+            );
+        assert_eq!(run_log(&mut world), ["input", "physics", "render", "audio"]);
+    }
+
+    #[test]
+    fn ordering_cycle_is_rejected_naming_its_systems() {
+        let mut world = World::new();
+        world
+            .add_system(SystemSchedule::Update, input.label("input"))
+            .add_system(
+                SystemSchedule::Update,
+                physics.label("physics").after("input"),
+            );
+
+        let Err(SystemError::OrderingCycle(cycle)) = world.try_add_system(
+            SystemSchedule::Update,
+            render.after("physics").before("input"),
+        ) else {
+            panic!("the cycle must be rejected");
+        };
+        let cycle: Vec<_> = cycle
+            .iter()
+            .map(|name| name.rsplit("::").next().unwrap())
+            .collect();
+        assert_eq!(cycle, ["input", "physics", "render"]);
+
+        // The rejected system isn't registered; the rest still runs.
+        assert_eq!(run_log(&mut world), ["input", "physics"]);
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Render;
+
+    #[test]
+    fn update_stages_run_in_order_including_custom_ones() {
+        let mut world = World::new();
+        world
+            .add_system(SystemSchedule::PostUpdate, audio)
+            .add_system(Render, render)
+            .add_system(SystemSchedule::Update, physics)
+            .add_system(SystemSchedule::PreUpdate, input);
+        // `Render` isn't an update stage until it's added as one.
+        assert_eq!(run_log(&mut world), ["input", "physics", "audio"]);
+
+        world.add_stage_after(SystemSchedule::Update, Render);
+        assert_eq!(run_log(&mut world), ["input", "physics", "render", "audio"]);
+    }
+
+    #[test]
+    fn custom_schedule_runs_by_label() {
+        let mut world = World::new();
+        world
+            .add_system(Render, render)
+            .add_system(SystemSchedule::Update, physics)
+            .add_resource(Log::new());
+
+        world.run_schedule(Render);
+        assert_eq!(*world.get_resource::<Log>().unwrap(), ["render"]);
+    }
+}
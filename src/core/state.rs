@@ -0,0 +1,164 @@
+use std::{fmt::Debug, hash::Hash};
+
+use super::world::World;
+
+/// Implemented for every type usable as an application state (e.g. a `GameState` enum):
+/// `Debug + Clone + Eq + Hash`, so its values can label [`OnEnter`]/[`OnExit`] schedules.
+pub trait States: Debug + Clone + Eq + Hash + Send + Sync + 'static {}
+
+impl<T: Debug + Clone + Eq + Hash + Send + Sync + 'static> States for T {}
+
+/// The current value of state `S`, as a resource. Inserted by
+/// [`World::init_state`](super::world::World::init_state); changed only through
+/// [`NextState`], at
+/// [`SystemSchedule::StateTransition`](super::system::SystemSchedule::StateTransition).
+pub struct State<S: States>(pub(crate) S);
+
+impl<S: States> State<S> {
+    /// Returns the current state.
+    pub fn get(&self) -> &S {
+        &self.0
+    }
+}
+
+/// The state `S` will transition to at the next
+/// [`SystemSchedule::StateTransition`](super::system::SystemSchedule::StateTransition), as a
+/// resource. Inserted by [`World::init_state`](super::world::World::init_state).
+pub struct NextState<S: States>(pub(crate) Option<S>);
+
+impl<S: States> NextState<S> {
+    /// Queues a transition to `state`, replacing any transition already queued.
+    pub fn set(&mut self, state: S) {
+        self.0 = Some(state);
+    }
+
+    /// Returns the queued transition, if any.
+    pub fn get(&self) -> Option<&S> {
+        self.0.as_ref()
+    }
+}
+
+/// Schedule run once when state `S` enters the given value, e.g. to spawn a menu.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnEnter<S: States>(pub S);
+
+/// Schedule run once when state `S` exits the given value, e.g. to despawn a menu.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnExit<S: States>(pub S);
+
+/// Schedule run once when state `S` goes from `from` to `to`, between [`OnExit`] and
+/// [`OnEnter`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnTransition<S: States> {
+    pub from: S,
+    pub to: S,
+}
+
+/// Applies the transition queued in [`NextState<S>`], if any, running [`OnExit`] for the old
+/// value, then [`OnTransition`] and [`OnEnter`] for the new one with [`State<S>`] already
+/// updated. The first call enters the initial state first, running its [`OnEnter`].
+pub(crate) fn apply_transition<S: States>(entered: &mut bool, world: &World) {
+    let (Some(mut state), Some(mut next)) = (
+        world.get_resource::<State<S>>(),
+        world.get_resource::<NextState<S>>(),
+    ) else {
+        return;
+    };
+    if !*entered {
+        *entered = true;
+        world.run_schedule(OnEnter(state.0.clone()));
+    }
+    let Some(to) = next.0.take() else {
+        return;
+    };
+    if to == state.0 {
+        return;
+    }
+
+    world.run_schedule(OnExit(state.0.clone()));
+    let from = std::mem::replace(&mut state.0, to.clone());
+    world.run_schedule(OnTransition {
+        from,
+        to: to.clone(),
+    });
+    world.run_schedule(OnEnter(to));
+}
+
+#[cfg(test)]
+mod state_test {
+    use super::{NextState, OnEnter, OnExit, OnTransition, State};
+    use crate::core::{
+        condition::in_state,
+        resources::Resource,
+        system::{IntoSystemConfig, SystemSchedule},
+        world::World,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Screen {
+        Menu,
+        Game,
+    }
+
+    type Log = Vec<&'static str>;
+
+    fn logger(event: &'static str) -> impl Fn(Resource<Log>) + Send + Sync + 'static {
+        move |mut log: Resource<Log>| log.push(event)
+    }
+
+    #[test]
+    fn transitions_run_exit_transition_and_enter_schedules() {
+        let mut world = World::new();
+        world
+            .add_resource(Log::new())
+            .init_state(Screen::Menu)
+            .add_system(OnEnter(Screen::Game), logger("enter game"))
+            .add_system(
+                OnTransition {
+                    from: Screen::Menu,
+                    to: Screen::Game,
+                },
+                logger("menu -> game"),
+            )
+            .add_system(OnExit(Screen::Menu), logger("exit menu"))
+            .add_system(OnEnter(Screen::Menu), logger("enter menu"));
+
+        world.run_update();
+        world.run_update();
+        world
+            .get_resource::<NextState<Screen>>()
+            .unwrap()
+            .set(Screen::Game);
+        world.run_update();
+
+        assert_eq!(
+            *world.get_resource::<Log>().unwrap(),
+            ["enter menu", "exit menu", "menu -> game", "enter game"]
+        );
+        assert_eq!(
+            *world.get_resource::<State<Screen>>().unwrap().get(),
+            Screen::Game
+        );
+    }
+
+    #[test]
+    fn transition_queued_in_pre_update_applies_before_update() {
+        fn start_game(mut next: Resource<NextState<Screen>>) {
+            next.set(Screen::Game);
+        }
+
+        fn play(mut frames: Resource<u32>) {
+            *frames += 1;
+        }
+
+        let mut world = World::new();
+        world
+            .add_resource(0u32)
+            .init_state(Screen::Menu)
+            .add_system(SystemSchedule::PreUpdate, start_game)
+            .add_system(SystemSchedule::Update, play.run_if(in_state(Screen::Game)));
+
+        world.run_update();
+        assert_eq!(*world.get_resource::<u32>().unwrap(), 1);
+    }
+}
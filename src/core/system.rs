@@ -1,44 +1,452 @@
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
-use std::{any::TypeId, cell::RefCell, rc::Rc};
+use std::error::Error;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
 
-use super::{access::AccessKey, coordinator::Coordinator, world::World};
+use super::{
+    access::{AccessKey, SystemAccess},
+    change_detection::SystemTicks,
+    command::CommandQueue,
+    condition::{BoxedCondition, Condition},
+    coordinator::Coordinator,
+    error::{SystemError, SystemFailure},
+    error_handler::{self, ErrorHandler},
+    label::SystemLabel,
+    local::LocalStorage,
+    schedule::{Schedule, ScheduleLabel},
+    state::{self, States},
+    world::World,
+};
 
-/// When a system runs, relative to [`World::run_startup`](super::world::World::run_startup),
+/// The built-in [`ScheduleLabel`]s: when a system runs, relative to
+/// [`World::run_startup`](super::world::World::run_startup),
 /// [`World::run_update`](super::world::World::run_update) and
 /// [`World::run_shutdown`](super::world::World::run_shutdown).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SystemSchedule {
     Startup,
+    /// Every frame, before [`SystemSchedule::Update`] (e.g. input handling).
+    PreUpdate,
+    /// Every frame, after [`SystemSchedule::PreUpdate`]: not a schedule systems are added to
+    /// (adding one fails with [`SystemError::StateTransitionSchedule`]), but the point where
+    /// queued [`NextState`](super::state::NextState) transitions are applied, running the
+    /// [`OnExit`](super::state::OnExit), [`OnTransition`](super::state::OnTransition) and
+    /// [`OnEnter`](super::state::OnEnter) schedules.
+    StateTransition,
+    /// Zero or more times a frame, after [`SystemSchedule::StateTransition`]: once per fixed
+    /// step accumulated in the [`FixedTime`](super::time::FixedTime) resource (e.g.
+    /// deterministic physics).
+    FixedUpdate,
     Update,
+    /// Every frame, after [`SystemSchedule::Update`] (e.g. rendering).
+    PostUpdate,
     Shutdown,
 }
 
 /// Implemented for every type a system function can take as an argument (e.g.
-/// `&EntityManager`, `Query<...>`, `Resource<T>`). Implementors fetch themselves from the
-/// [`Coordinator`] shared by every system, so systems never need a direct reference to
-/// [`World`].
+/// `&EntityManager`, `Query<...>`, `Resource<T>`). Implementors declare what they access when
+/// the system is registered, then fetch themselves from the [`Coordinator`] shared by every
+/// system, so systems never need a direct reference to [`World`].
 pub trait SystemParam {
-    fn get_param(coordinator: Rc<RefCell<Coordinator>>) -> Self;
+    /// Declares what this parameter accesses. Called once, when a system taking it is
+    /// registered.
+    fn access(access: &mut SystemAccess);
+
+    /// Fetches the parameter for one run of the system described by `meta`.
+    fn get_param(coordinator: &Coordinator, meta: &SystemMeta) -> Self;
 }
 
 /// Implemented for plain functions/closures whose arguments are all [`SystemParam`]s (up to 26
 /// of them), letting them be registered via
-/// [`World::add_system`](super::world::World::add_system).
+/// [`World::add_system`](super::world::World::add_system) (when they return a
+/// [`SystemOutput`]) or used as a run [`Condition`] (when they
+/// return `bool`).
 pub trait System<P> {
-    fn run(&self, coordinator: Rc<RefCell<Coordinator>>);
+    /// What the function returns.
+    type Out;
+
+    fn run(&self, coordinator: &Coordinator, meta: &SystemMeta) -> Self::Out;
+
+    /// Collects the accesses declared by every parameter of the system.
+    fn access(&self) -> SystemAccess;
+
+    /// Combines this system and `next` into one system running them one after the other,
+    /// `next` receiving what this one returns through its [`In`] parameter, which must be its
+    /// first. The result is registered like any other system, with both systems' accesses; each
+    /// keeps its own [`Local`](super::local::Local)s:
+    ///
+    /// ```
+    /// # use dark_iron_ecs::core::{system::{In, System, SystemSchedule}, world::World};
+    /// # use dark_iron_ecs::core::entity_manager::EntityManager;
+    /// fn count(entities: &EntityManager) -> usize {
+    ///     # let _ = entities;
+    ///     0
+    /// }
+    ///
+    /// fn report(In(count): In<usize>, _: &EntityManager) {
+    ///     println!("{count} entities");
+    /// }
+    ///
+    /// World::new().add_system(SystemSchedule::Update, count.pipe(report));
+    /// ```
+    ///
+    /// Piping into a system that doesn't take an `In<Self::Out>` doesn't compile:
+    ///
+    /// ```compile_fail
+    /// # use dark_iron_ecs::core::{system::{In, System}, entity_manager::EntityManager};
+    /// fn count(_: &EntityManager) -> usize {
+    ///     0
+    /// }
+    ///
+    /// fn greet(In(name): In<String>, _: &EntityManager) {
+    ///     println!("hello {name}");
+    /// }
+    ///
+    /// let _ = count.pipe(greet);
+    /// ```
+    fn pipe<Q, B>(self, next: B) -> Pipe<Self, B, P, Q>
+    where
+        Self: Sized,
+        Self::Out: Send + 'static,
+        B: System<Q>,
+        Q: PipeInput<Self::Out>,
+    {
+        let first = SystemMeta::new(std::any::type_name::<Self>(), self.access());
+        let second = SystemMeta::new(std::any::type_name::<B>(), next.access());
+        Pipe {
+            first: self,
+            second: next,
+            metas: [Mutex::new(first), Mutex::new(second)],
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Implemented for the parameters of systems whose first parameter is an `In<T>`, which can
+/// therefore be piped into from a system returning `T`; see [`System::pipe`].
+pub trait PipeInput<T> {}
+
+macro_rules! impl_pipe_input {
+    () => {
+        impl<Input> PipeInput<Input> for (In<Input>,) {}
+    };
+    ( $head:ident $(, $tail:ident)* ) => {
+        impl<Input, $head, $($tail),*> PipeInput<Input> for (In<Input>, $head, $($tail,)*) {}
+        impl_pipe_input!($($tail),*);
+    };
+}
+
+impl_pipe_input!(B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z);
+
+/// A [`Pipe`] takes the input of its first system.
+impl<Input, PA: PipeInput<Input>, PB> PipeInput<Input> for fn(PA, PB) {}
+
+/// A system parameter holding what the system piped into this one returned; see
+/// [`System::pipe`]. A system takes at most one, as its first parameter, and can only be
+/// registered piped.
+pub struct In<T>(pub T);
+
+impl<T> Deref for In<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for In<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Send + 'static> SystemParam for In<T> {
+    fn access(access: &mut SystemAccess) {
+        access.add_input(TypeId::of::<T>(), std::any::type_name::<T>());
+    }
+
+    fn get_param(_coordinator: &Coordinator, meta: &SystemMeta) -> Self {
+        let input = meta.input.lock().unwrap().take();
+        In(*input
+            .and_then(|input| input.downcast::<T>().ok())
+            .expect("In<T> fetched outside a piped system"))
+    }
+}
+
+/// Two systems combined by [`System::pipe`], running as one.
+pub struct Pipe<A, B, PA, PB> {
+    first: A,
+    second: B,
+    /// The own [`SystemMeta`]s of `first` and `second`, keeping their locals, inputs and
+    /// commands apart. They share the change-tick window of the pipe's runs.
+    metas: [Mutex<SystemMeta>; 2],
+    marker: PhantomData<fn(PA, PB)>,
+}
+
+impl<A, B, PA, PB> System<fn(PA, PB)> for Pipe<A, B, PA, PB>
+where
+    A: System<PA>,
+    A::Out: Send + 'static,
+    B: System<PB>,
+{
+    type Out = B::Out;
+
+    fn run(&self, coordinator: &Coordinator, meta: &SystemMeta) -> B::Out {
+        let [first_meta, second_meta] = &self.metas;
+        let mut first_meta = first_meta.lock().unwrap();
+        first_meta.begin_nested_run(meta);
+        *first_meta.input.get_mut().unwrap() = meta.input.lock().unwrap().take();
+        let out = self.first.run(coordinator, &first_meta);
+        first_meta.end_nested_run(meta);
+
+        let mut second_meta = second_meta.lock().unwrap();
+        second_meta.begin_nested_run(meta);
+        *second_meta.input.get_mut().unwrap() = Some(Box::new(out));
+        let out = self.second.run(coordinator, &second_meta);
+        second_meta.end_nested_run(meta);
+        out
+    }
+
+    fn access(&self) -> SystemAccess {
+        let mut access = self.first.access();
+        access.extend(self.second.access());
+        access
+    }
+}
+
+/// Per-system state kept by [`SystemManager`] between runs and handed to every
+/// [`SystemParam::get_param`] call.
+pub struct SystemMeta {
+    pub(crate) name: &'static str,
+    pub(crate) access: SystemAccess,
+    /// Change-tick window of the current (or latest) run.
+    pub(crate) ticks: SystemTicks,
+    /// Whether the system ran at least once, so `ticks` holds a real run.
+    has_run: bool,
+    /// Commands recorded by the system, applied by [`SystemManager`] at the next sync point.
+    pub(crate) command_queue: Arc<Mutex<CommandQueue>>,
+    /// What the first system of a [`Pipe`] returned, until the second fetches it as [`In`].
+    input: Mutex<Option<Box<dyn Any + Send>>>,
+    /// Values of the system's [`Local`](super::local::Local) parameters.
+    pub(crate) locals: Mutex<LocalStorage>,
+}
+
+impl SystemMeta {
+    fn new(name: &'static str, access: SystemAccess) -> Self {
+        Self {
+            name,
+            access,
+            ticks: SystemTicks::default(),
+            has_run: false,
+            command_queue: Arc::default(),
+            input: Mutex::default(),
+            locals: Mutex::default(),
+        }
+    }
+
+    /// Opens the change-tick window of the next run, stamping it with `change_tick` and
+    /// advancing it so no other run shares the tick. The previous run's tick becomes
+    /// `last_run`; everything is new to the first run.
+    pub(crate) fn begin_run(&mut self, change_tick: &mut u32) {
+        self.locals.get_mut().unwrap().rewind();
+        self.ticks = if self.has_run {
+            SystemTicks {
+                last_run: self.ticks.this_run,
+                this_run: *change_tick,
+            }
+        } else {
+            SystemTicks::first_run(*change_tick)
+        };
+        self.has_run = true;
+        *change_tick = change_tick.wrapping_add(1);
+    }
+
+    /// Opens the next run of a system nested in the one described by `outer` (see [`Pipe`]),
+    /// within the change-tick window of `outer`'s current run.
+    fn begin_nested_run(&mut self, outer: &SystemMeta) {
+        self.locals.get_mut().unwrap().rewind();
+        self.ticks = outer.ticks;
+        self.has_run = true;
+    }
+
+    /// Moves the commands recorded by a nested system's run to the end of `outer`'s queue,
+    /// which is the one applied at the next sync point.
+    fn end_nested_run(&mut self, outer: &SystemMeta) {
+        let mut nested = self.command_queue.lock().unwrap();
+        outer.command_queue.lock().unwrap().append(&mut nested);
+    }
+
+    /// Clamps the ticks of the latest run to at most
+    /// [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before `change_tick`.
+    pub(crate) fn clamp_ticks(&mut self, change_tick: u32) {
+        self.ticks.clamp(change_tick);
+    }
+}
+
+/// Implemented for what a registered system may return: `()`, or a `Result<(), E>` whose error
+/// is handed to the [`SystemManager`]'s error handler (see
+/// [`World::set_error_handler`](super::world::World::set_error_handler)). `E` is any
+/// `Error + Send + Sync` type, or a `Box<dyn Error + Send + Sync>` to mix several with `?`.
+pub trait SystemOutput {
+    fn into_result(self) -> SystemResult;
+}
+
+/// What a registered system returns once type-erased.
+pub type SystemResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+impl SystemOutput for () {
+    fn into_result(self) -> SystemResult {
+        Ok(())
+    }
 }
 
-/// Converts a [`System`] into the boxed, type-erased closure form stored by [`SystemManager`].
+impl<E: Into<Box<dyn Error + Send + Sync>>> SystemOutput for Result<(), E> {
+    fn into_result(self) -> SystemResult {
+        self.map_err(Into::into)
+    }
+}
+
+type SystemFunction<Out> = Box<dyn Fn(&Coordinator, &SystemMeta) -> Out + Send + Sync>;
+
+/// A system in the type-erased form stored by [`SystemManager`], along with its [`SystemMeta`].
+/// `Out` is what the system returns: a [`SystemResult`], or `bool` for a run
+/// [`Condition`].
+pub struct BoxedSystem<Out = SystemResult> {
+    function: SystemFunction<Out>,
+    pub(crate) meta: SystemMeta,
+}
+
+impl<Out> BoxedSystem<Out> {
+    /// Boxes `system`, collecting its parameters' accesses.
+    pub(crate) fn new<P, F>(system: F) -> Self
+    where
+        F: System<P, Out = Out> + Send + Sync + 'static,
+        Out: 'static,
+    {
+        Self::map(system, |out| out)
+    }
+
+    /// Boxes `system`, converting what it returns via `map`.
+    fn map<P, F>(system: F, map: fn(F::Out) -> Out) -> Self
+    where
+        F: System<P> + Send + Sync + 'static,
+        F::Out: 'static,
+        Out: 'static,
+    {
+        let meta = SystemMeta::new(std::any::type_name::<F>(), system.access());
+        BoxedSystem {
+            function: Box::new(move |coordinator, meta| map(system.run(coordinator, meta))),
+            meta,
+        }
+    }
+
+    /// The name of the system's function, as given by `std::any::type_name`.
+    pub fn name(&self) -> &'static str {
+        self.meta.name
+    }
+
+    /// Everything the system's parameters access.
+    pub fn access(&self) -> &SystemAccess {
+        &self.meta.access
+    }
+
+    /// Runs the system once.
+    pub(crate) fn run(&self, coordinator: &Coordinator) -> Out {
+        (self.function)(coordinator, &self.meta)
+    }
+}
+
+/// Converts a [`System`] into the boxed, type-erased form stored by [`SystemManager`].
 pub trait IntoSystem<P> {
-    fn system(self) -> Box<dyn FnMut(Rc<RefCell<Coordinator>>)>;
+    fn system(self) -> BoxedSystem;
 }
 
 impl<F, P> IntoSystem<P> for F
 where
-    F: System<P> + 'static,
+    F: System<P> + Send + Sync + 'static,
+    F::Out: SystemOutput + 'static,
 {
-    fn system(self) -> Box<dyn FnMut(Rc<RefCell<Coordinator>>)> {
-        Box::new(move |coordinator| self.run(coordinator))
+    fn system(self) -> BoxedSystem {
+        BoxedSystem::map(self, SystemOutput::into_result)
+    }
+}
+
+/// A system together with its labels and ordering constraints, as accepted by
+/// [`World::add_system`](super::world::World::add_system). Built by the
+/// [`IntoSystemConfig`] methods.
+pub struct SystemConfig {
+    pub(crate) system: BoxedSystem,
+    /// Conditions that must all hold for the system to run.
+    pub(crate) conditions: Vec<BoxedCondition>,
+    pub(crate) labels: Vec<Box<dyn SystemLabel>>,
+    pub(crate) before: Vec<Box<dyn SystemLabel>>,
+    pub(crate) after: Vec<Box<dyn SystemLabel>>,
+}
+
+/// Implemented for every [`IntoSystem`] and for [`SystemConfig`] itself, letting a system be
+/// labeled and ordered relative to others as it's registered:
+///
+/// ```
+/// # use dark_iron_ecs::core::{system::{IntoSystemConfig, SystemSchedule}, world::World};
+/// # use dark_iron_ecs::core::entity_manager::EntityManager;
+/// # fn physics(_: &EntityManager) {}
+/// # fn render(_: &EntityManager) {}
+/// World::new()
+///     .add_system(SystemSchedule::Update, render.after("physics"))
+///     .add_system(SystemSchedule::Update, physics.label("physics"));
+/// ```
+pub trait IntoSystemConfig<P>: Sized {
+    fn into_config(self) -> SystemConfig;
+
+    /// Labels the system, so others can be ordered relative to it. A label may be shared by
+    /// several systems.
+    fn label(self, label: impl SystemLabel) -> SystemConfig {
+        let mut config = self.into_config();
+        config.labels.push(Box::new(label));
+        config
+    }
+
+    /// Runs the system before every system labeled `label`. Ignored if there is none.
+    fn before(self, label: impl SystemLabel) -> SystemConfig {
+        let mut config = self.into_config();
+        config.before.push(Box::new(label));
+        config
+    }
+
+    /// Runs the system after every system labeled `label`. Ignored if there is none.
+    fn after(self, label: impl SystemLabel) -> SystemConfig {
+        let mut config = self.into_config();
+        config.after.push(Box::new(label));
+        config
+    }
+
+    /// Only runs the system, each time its turn comes, if `condition` returns `true`; a
+    /// skipped system doesn't even fetch its parameters. Calling this again adds another
+    /// condition, which must hold too.
+    fn run_if<M>(self, condition: impl Condition<M>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.conditions.push(condition.into_condition());
+        config
+    }
+}
+
+impl<P, F: IntoSystem<P>> IntoSystemConfig<P> for F {
+    fn into_config(self) -> SystemConfig {
+        SystemConfig {
+            system: self.system(),
+            conditions: Vec::new(),
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+}
+
+impl IntoSystemConfig<SystemConfig> for SystemConfig {
+    fn into_config(self) -> SystemConfig {
+        self
     }
 }
 
@@ -46,15 +454,22 @@ macro_rules! impl_system {
     ( $head:ident ) => {
         #[allow(non_snake_case)]
         #[allow(unused_variables)]
-        impl<Func, $head> System<($head,)> for Func
+        impl<Func, Out, $head> System<($head,)> for Func
         where
-            Func: Fn($head),
+            Func: Fn($head) -> Out,
             $head: SystemParam,
         {
-            fn run(&self, coordinator: Rc<RefCell<Coordinator>>) {
-                coordinator.borrow().access_tracker.borrow_mut().clear();
-                let $head = $head::get_param(coordinator.clone());
-                self($head);
+            type Out = Out;
+
+            fn run(&self, coordinator: &Coordinator, meta: &SystemMeta) -> Out {
+                let $head = $head::get_param(coordinator, meta);
+                self($head)
+            }
+
+            fn access(&self) -> SystemAccess {
+                let mut access = SystemAccess::default();
+                $head::access(&mut access);
+                access
             }
         }
 
@@ -66,19 +481,29 @@ macro_rules! impl_system {
 
         #[allow(non_snake_case)]
         #[allow(unused_variables)]
-        impl<Func, $head, $($tail,)*> System<($head, $($tail,)*)> for Func
+        impl<Func, Out, $head, $($tail,)*> System<($head, $($tail,)*)> for Func
         where
-            Func: Fn($head, $($tail),*),
+            Func: Fn($head, $($tail),*) -> Out,
             $head: SystemParam,
             $($tail: SystemParam,)*
         {
-            fn run(&self, coordinator: Rc<RefCell<Coordinator>>) {
-                coordinator.borrow().access_tracker.borrow_mut().clear();
-                let $head = $head::get_param(coordinator.clone());
+            type Out = Out;
+
+            fn run(&self, coordinator: &Coordinator, meta: &SystemMeta) -> Out {
+                let $head = $head::get_param(coordinator, meta);
                 $(
-                    let $tail = $tail::get_param(coordinator.clone());
+                    let $tail = $tail::get_param(coordinator, meta);
                 )*
-                self($head, $($tail),*);
+                self($head, $($tail),*)
+            }
+
+            fn access(&self) -> SystemAccess {
+                let mut access = SystemAccess::default();
+                $head::access(&mut access);
+                $(
+                    $tail::access(&mut access);
+                )*
+                access
             }
         }
     }
@@ -86,83 +511,343 @@ macro_rules! impl_system {
 
 impl_system!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z);
 
-type SystemFunctionMap = HashMap<SystemSchedule, Vec<Box<dyn FnMut(Rc<RefCell<Coordinator>>)>>>;
+/// Marks the [`System`] impl of exclusive systems: functions taking `&mut World` as their
+/// only parameter. They conflict with every other system, so they always run alone, on the
+/// thread running the schedule, and may do anything with the world — add systems or
+/// resources, build extensions, run other schedules. Running the schedule an exclusive system
+/// belongs to from within it does nothing, since that schedule is already running.
+///
+/// ```
+/// # use dark_iron_ecs::core::{system::SystemSchedule, world::World};
+/// fn spawn_wave(world: &mut World) {
+///     world.create_entity((0u32,));
+/// }
+///
+/// World::new().add_system(SystemSchedule::Update, spawn_wave);
+/// ```
+pub struct Exclusive;
 
-/// Owns every registered system, grouped by [`SystemSchedule`]. Most callers interact with it
+impl<Func, Out> System<Exclusive> for Func
+where
+    Func: Fn(&mut World) -> Out,
+{
+    type Out = Out;
+
+    fn run(&self, coordinator: &Coordinator, _meta: &SystemMeta) -> Out {
+        self(&mut World::from_coordinator(Arc::new(coordinator.clone())))
+    }
+
+    fn access(&self) -> SystemAccess {
+        let mut access = SystemAccess::default();
+        access.add_exclusive();
+        access
+    }
+}
+
+/// Owns every registered system, grouped by [`ScheduleLabel`]. Most callers interact with it
 /// indirectly through [`World`] rather than directly.
+///
+/// Within a schedule, systems run in the order their constraints dictate (see [`Schedule`]),
+/// and consecutive systems whose [`SystemAccess`]es don't conflict run in parallel, on a pool
+/// of worker threads.
 pub struct SystemManager {
-    pub systems: SystemFunctionMap,
+    pub systems: HashMap<Box<dyn ScheduleLabel>, Schedule>,
+    /// Schedules run, in order, by [`World::run_update`].
+    update_stages: Vec<Box<dyn ScheduleLabel>>,
+    /// Transition appliers of every state added via [`SystemManager::add_state`], keyed by
+    /// the state's type.
+    state_transitions: Vec<(TypeId, StateTransition)>,
+    /// Called with every error a system returns.
+    error_handler: ErrorHandler,
 }
 
+pub(crate) type StateTransition = Box<dyn FnMut(&World) + Send + Sync>;
+
 impl SystemManager {
-    /// Creates an empty manager with no systems registered.
+    /// Creates an empty manager with no systems registered, whose update stages are
+    /// [`SystemSchedule::PreUpdate`], [`SystemSchedule::StateTransition`],
+    /// [`SystemSchedule::FixedUpdate`], [`SystemSchedule::Update`] and
+    /// [`SystemSchedule::PostUpdate`].
     pub fn new() -> Self {
         SystemManager {
             systems: HashMap::new(),
+            update_stages: vec![
+                Box::new(SystemSchedule::PreUpdate),
+                Box::new(SystemSchedule::StateTransition),
+                Box::new(SystemSchedule::FixedUpdate),
+                Box::new(SystemSchedule::Update),
+                Box::new(SystemSchedule::PostUpdate),
+            ],
+            state_transitions: Vec::new(),
+            error_handler: Arc::new(error_handler::panic_on_error),
         }
     }
 
-    /// Registers `system` to run during `system_schedule`.
-    pub fn add_system<P, F>(&mut self, system_schedule: SystemSchedule, system: F)
-    where
-        F: IntoSystem<P>,
-    {
+    /// Returns the schedule labeled `label`, if any system was registered under it.
+    pub fn schedule(&self, label: impl ScheduleLabel) -> Option<&Schedule> {
+        self.systems.get(&label as &dyn ScheduleLabel)
+    }
+
+    /// Registers `system` to run during the schedule labeled `schedule`.
+    ///
+    /// # Panics
+    /// Panics if the system is rejected; see [`SystemManager::try_add_system`].
+    pub fn add_system<P>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        system: impl IntoSystemConfig<P>,
+    ) {
+        if let Err(error) = self.try_add_system(schedule, system) {
+            panic!("{error}");
+        }
+    }
+
+    /// Registers `system` to run during the schedule labeled `schedule`, after checking its
+    /// parameters' accesses don't conflict with each other and its ordering constraints don't
+    /// form a cycle. Returns [`SystemError::ConflictingAccess`] or
+    /// [`SystemError::OrderingCycle`] otherwise, leaving the manager unchanged, so invalid
+    /// systems are caught before the first frame rather than when they first run. Systems
+    /// can't be added to [`SystemSchedule::StateTransition`]
+    /// ([`SystemError::StateTransitionSchedule`]).
+    pub fn try_add_system<P>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        system: impl IntoSystemConfig<P>,
+    ) -> Result<(), SystemError> {
+        let config = system.into_config();
+        Self::check_schedule(&schedule, std::slice::from_ref(&config))?;
         self.systems
-            .entry(system_schedule)
+            .entry(Box::new(schedule))
             .or_default()
-            .push(system.system());
+            .add(vec![config], false)
     }
 
-    /// Runs every system registered under [`SystemSchedule::Startup`].
-    pub fn run_startup_systems(&mut self, world: &World) {
-        if let Some(systems) = self.systems.get_mut(&SystemSchedule::Startup) {
-            for system in systems.iter_mut() {
-                system(world.coordinator.clone().expect(
-                    "Coordinator not initialized - call World::new() before running systems",
-                ));
-            }
+    /// Registers a bundle of systems to run during the schedule labeled `schedule`.
+    ///
+    /// # Panics
+    /// Panics if any of the systems is rejected; see [`SystemManager::try_add_systems`].
+    pub fn add_systems<P>(&mut self, schedule: impl ScheduleLabel, systems: impl SystemBundle<P>) {
+        if let Err(error) = self.try_add_systems(schedule, systems) {
+            panic!("{error}");
         }
     }
 
-    /// Runs every system registered under [`SystemSchedule::Update`].
-    pub fn run_update_systems(&mut self, world: &World) {
-        if let Some(systems) = self.systems.get_mut(&SystemSchedule::Update) {
-            for system in systems.iter_mut() {
-                system(world.coordinator.clone().expect(
-                    "Coordinator not initialized - call World::new() before running systems",
-                ));
+    /// Like [`SystemManager::try_add_system`], for a bundle of systems. Registers either all of
+    /// them or, on error, none.
+    pub fn try_add_systems<P>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        systems: impl SystemBundle<P>,
+    ) -> Result<(), SystemError> {
+        let systems = systems.into_configs();
+        Self::check_schedule(&schedule, &systems.configs)?;
+        self.systems
+            .entry(Box::new(schedule))
+            .or_default()
+            .add(systems.configs, systems.chained)
+    }
+
+    /// Rejects systems added to [`SystemSchedule::StateTransition`], which [`World::run_update`]
+    /// never runs systems of.
+    fn check_schedule(
+        schedule: &dyn ScheduleLabel,
+        configs: &[SystemConfig],
+    ) -> Result<(), SystemError> {
+        let state_transition: &dyn ScheduleLabel = &SystemSchedule::StateTransition;
+        match configs.first() {
+            Some(config) if *schedule == *state_transition => {
+                Err(SystemError::StateTransitionSchedule(config.system.name()))
             }
+            _ => Ok(()),
         }
     }
 
-    /// Runs every system registered under [`SystemSchedule::Shutdown`].
-    pub fn run_shutdown_systems(&mut self, world: &World) {
-        if let Some(systems) = self.systems.get_mut(&SystemSchedule::Shutdown) {
-            for system in systems.iter_mut() {
-                system(world.coordinator.clone().expect(
-                    "Coordinator not initialized - call World::new() before running systems",
-                ));
-            }
+    /// Sets the function called with every error a system returns, replacing
+    /// [`panic_on_error`](error_handler::panic_on_error) or any handler set before.
+    pub fn set_error_handler(
+        &mut self,
+        error_handler: impl Fn(&World, SystemFailure) + Send + Sync + 'static,
+    ) {
+        self.error_handler = Arc::new(error_handler);
+    }
+
+    /// Returns the schedules [`World::run_update`] runs, in order.
+    pub fn update_stages(&self) -> &[Box<dyn ScheduleLabel>] {
+        &self.update_stages
+    }
+
+    /// Makes `stage` one of the update stages, run right before `before`.
+    ///
+    /// # Panics
+    /// Panics if `before` isn't an update stage, or `stage` already is.
+    pub fn add_stage_before(&mut self, before: impl ScheduleLabel, stage: impl ScheduleLabel) {
+        let index = self.stage_index(&before);
+        self.insert_stage(index, stage);
+    }
+
+    /// Makes `stage` one of the update stages, run right after `after`.
+    ///
+    /// # Panics
+    /// Panics if `after` isn't an update stage, or `stage` already is.
+    pub fn add_stage_after(&mut self, after: impl ScheduleLabel, stage: impl ScheduleLabel) {
+        let index = self.stage_index(&after);
+        self.insert_stage(index + 1, stage);
+    }
+
+    fn stage_index(&self, stage: &dyn ScheduleLabel) -> usize {
+        self.update_stages
+            .iter()
+            .position(|existing| **existing == *stage)
+            .unwrap_or_else(|| panic!("{stage:?} is not an update stage"))
+    }
+
+    fn insert_stage(&mut self, index: usize, stage: impl ScheduleLabel) {
+        let stage: Box<dyn ScheduleLabel> = Box::new(stage);
+        if self.update_stages.contains(&stage) {
+            panic!("{stage:?} is already an update stage");
+        }
+        self.update_stages.insert(index, stage);
+    }
+
+    /// Has [`SystemSchedule::StateTransition`] apply the transitions of state `S`, whose
+    /// [`State`](super::state::State) and [`NextState`](super::state::NextState) resources
+    /// must be added separately (see [`World::init_state`]). The first time, it enters the
+    /// initial state instead. Calling this again for the same `S` starts it over.
+    pub fn add_state<S: States>(&mut self) {
+        let mut entered = false;
+        let transition: StateTransition =
+            Box::new(move |world| state::apply_transition::<S>(&mut entered, world));
+        let type_id = TypeId::of::<S>();
+        match self.state_transitions.iter_mut().find(|(id, _)| *id == type_id) {
+            Some((_, existing)) => *existing = transition,
+            None => self.state_transitions.push((type_id, transition)),
+        }
+    }
+
+    /// Removes the schedule labeled `label` while it runs, along with the error handler to
+    /// run it with, so systems running meanwhile (e.g. exclusive ones) can borrow the manager.
+    pub(crate) fn take_schedule(
+        &mut self,
+        label: &dyn ScheduleLabel,
+    ) -> Option<(Box<dyn ScheduleLabel>, Schedule, ErrorHandler)> {
+        let (label, schedule) = self.systems.remove_entry(label)?;
+        Some((label, schedule, self.error_handler.clone()))
+    }
+
+    /// Puts back a schedule removed by [`SystemManager::take_schedule`], along with any
+    /// system added under its label while it ran.
+    ///
+    /// # Panics
+    /// Panics if the systems added meanwhile form an ordering cycle with the others.
+    pub(crate) fn restore_schedule(
+        &mut self,
+        label: Box<dyn ScheduleLabel>,
+        mut schedule: Schedule,
+    ) {
+        if let Some(added) = self.systems.remove(&label)
+            && let Err(error) = schedule.append(added)
+        {
+            panic!("{error}");
         }
+        self.systems.insert(label, schedule);
+    }
+
+    /// Clamps the change ticks of every system and run condition to at most
+    /// [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before `change_tick`.
+    /// Schedules taken out by [`SystemManager::take_schedule`] are skipped.
+    pub(crate) fn clamp_change_ticks(&mut self, change_tick: u32) {
+        for schedule in self.systems.values_mut() {
+            schedule.clamp_change_ticks(change_tick);
+        }
+    }
+
+    /// Removes the transition appliers of every state while they run; see
+    /// [`SystemManager::take_schedule`].
+    pub(crate) fn take_state_transitions(&mut self) -> Vec<(TypeId, StateTransition)> {
+        std::mem::take(&mut self.state_transitions)
+    }
+
+    /// Puts back transition appliers removed by [`SystemManager::take_state_transitions`],
+    /// keeping any state added while they ran.
+    pub(crate) fn restore_state_transitions(
+        &mut self,
+        mut transitions: Vec<(TypeId, StateTransition)>,
+    ) {
+        transitions.append(&mut self.state_transitions);
+        self.state_transitions = transitions;
     }
 }
 
 impl SystemParam for &SystemManager {
-    fn get_param(world: Rc<RefCell<Coordinator>>) -> Self {
-        world.borrow().access_tracker.borrow_mut().track(
+    fn access(access: &mut SystemAccess) {
+        // Exclusive: the systems it exposes are being run (and mutated) by the executor.
+        access.add(
             AccessKey::Manager(TypeId::of::<SystemManager>()),
             false,
             "SystemManager",
         );
-        unsafe { &(*world.borrow().get_system_manager_mut()) }
+        access.add_exclusive();
+    }
+
+    fn get_param(coordinator: &Coordinator, _meta: &SystemMeta) -> Self {
+        unsafe { &(*coordinator.get_system_manager_mut()) }
+    }
+}
+
+/// Several systems registered together via
+/// [`World::add_systems`](super::world::World::add_systems), built by the [`SystemBundle`]
+/// methods.
+pub struct SystemConfigs {
+    configs: Vec<SystemConfig>,
+    /// Whether each system runs after the previous one.
+    chained: bool,
+}
+
+/// Implemented for tuples of [`IntoSystemConfig`]s (up to 26 elements) and for
+/// [`SystemConfigs`], letting [`World::add_systems`](super::world::World::add_systems)
+/// register several systems for the same schedule in one call.
+pub trait SystemBundle<P>: Sized {
+    fn into_configs(self) -> SystemConfigs;
+
+    /// Runs the systems one after the other, in the order listed.
+    fn chain(self) -> SystemConfigs {
+        let mut systems = self.into_configs();
+        systems.chained = true;
+        systems
+    }
+
+    /// Labels every system of the bundle.
+    fn label(self, label: impl SystemLabel) -> SystemConfigs {
+        self.configure(|config| config.labels.push(SystemLabel::dyn_clone(&label)))
+    }
+
+    /// Runs every system of the bundle before every system labeled `label`.
+    fn before(self, label: impl SystemLabel) -> SystemConfigs {
+        self.configure(|config| config.before.push(SystemLabel::dyn_clone(&label)))
+    }
+
+    /// Runs every system of the bundle after every system labeled `label`.
+    fn after(self, label: impl SystemLabel) -> SystemConfigs {
+        self.configure(|config| config.after.push(SystemLabel::dyn_clone(&label)))
+    }
+
+    /// Only runs each system of the bundle if its own copy of `condition` returns `true`.
+    fn run_if<M>(self, condition: impl Condition<M> + Clone) -> SystemConfigs {
+        self.configure(|config| config.conditions.push(condition.clone().into_condition()))
+    }
+
+    #[doc(hidden)]
+    fn configure(self, configure: impl Fn(&mut SystemConfig)) -> SystemConfigs {
+        let mut systems = self.into_configs();
+        systems.configs.iter_mut().for_each(configure);
+        systems
     }
 }
 
-/// Implemented for tuples of [`IntoSystem`]s (up to 26 elements), letting
-/// [`World::add_systems`](super::world::World::add_systems) register several systems for the
-/// same schedule in one call.
-pub trait SystemBundle<P> {
-    fn add_systems(self, system_schedule: SystemSchedule, system_manager: &mut SystemManager);
+impl SystemBundle<SystemConfigs> for SystemConfigs {
+    fn into_configs(self) -> SystemConfigs {
+        self
+    }
 }
 
 impl Default for SystemManager {
@@ -172,41 +857,33 @@ impl Default for SystemManager {
 }
 
 macro_rules! impl_system_bundle {
-
-    ( ($head:ident, $identifier:ident) ) => {
-        #[allow(non_snake_case)]
-        #[allow(unused_variables)]
-        impl<$identifier: 'static, $head: IntoSystem<$identifier>> SystemBundle<($identifier,)> for ($head,) {
-            fn add_systems(self, system_schedule: SystemSchedule, system_manager: &mut SystemManager) {
-                system_manager.add_system(system_schedule, self.0);
-            }
-        }
-    };
-
-    ( ($head:ident, $identifier:ident), $( ($tail:ident, $identifier_tail:ident) ),* ) => {
-
+    ( $(($system:ident, $marker:ident)),* ) => {
         #[allow(non_snake_case)]
-        #[allow(unused_variables)]
-        impl<$identifier: 'static, $head: IntoSystem<$identifier>, $($identifier_tail: 'static, $tail: IntoSystem<$identifier_tail>),*> SystemBundle<($identifier, $($identifier_tail),*)> for ($head, $($tail),*)
-        where
-            $($tail: IntoSystem<$identifier_tail>,)*
+        impl<$($marker, $system: IntoSystemConfig<$marker>),*> SystemBundle<($($marker,)*)>
+            for ($($system,)*)
         {
-            fn add_systems(self, system_schedule: SystemSchedule, system_manager: &mut SystemManager) {
-
-                let ($head, $($tail),*) = self;
-
-                system_manager.add_system(system_schedule, $head);
-                $(
-                    system_manager.add_system(system_schedule, $tail);
-                )*
+            fn into_configs(self) -> SystemConfigs {
+                let ($($system,)*) = self;
+                SystemConfigs {
+                    configs: vec![$($system.into_config()),*],
+                    chained: false,
+                }
             }
         }
+    };
+}
 
-        impl_system_bundle!($(($tail, $identifier_tail)),*);
+macro_rules! impl_system_bundles {
+    ( ($head:ident, $head_marker:ident) ) => {
+        impl_system_bundle!(($head, $head_marker));
+    };
+    ( ($head:ident, $head_marker:ident), $( ($tail:ident, $tail_marker:ident) ),* ) => {
+        impl_system_bundle!(($head, $head_marker), $(($tail, $tail_marker)),*);
+        impl_system_bundles!($(($tail, $tail_marker)),*);
     };
 }
 
-impl_system_bundle!(
+impl_system_bundles!(
     (A, A1),
     (B, B1),
     (C, C1),
@@ -234,3 +911,176 @@ impl_system_bundle!(
     (Y, Y1),
     (Z, Z1)
 );
+
+#[cfg(test)]
+mod system_test {
+    use super::{In, System, SystemManager, SystemSchedule};
+    use crate::core::{
+        access::SystemAccess,
+        command::Commands,
+        entity_manager::EntityManager,
+        error::{QueryError, SystemError},
+        local::Local,
+        query::Query,
+        resources::Resource,
+        world::World,
+    };
+
+    struct Position;
+
+    #[test]
+    fn conflicting_system_is_rejected_at_registration() {
+        fn conflicting(_: &mut EntityManager, _: Query<(&Position,)>) {
+            unreachable!("never registered, so never run");
+        }
+
+        let mut manager = SystemManager::new();
+        let error = manager
+            .try_add_system(SystemSchedule::Update, conflicting)
+            .unwrap_err();
+        let SystemError::ConflictingAccess { system, conflict } = error else {
+            panic!("expected a conflicting access, got {error}");
+        };
+        assert!(system.ends_with("conflicting"));
+        assert!(conflict.contains("EntityManager"));
+        assert!(manager.systems.values().all(|schedule| schedule.is_empty()));
+    }
+
+    fn count_positions(query: Query<(&Position,)>) -> Result<usize, QueryError> {
+        Ok(query.iter().count())
+    }
+
+    fn record(In(result): In<Result<usize, QueryError>>, mut log: Resource<Vec<String>>) {
+        log.push(match result {
+            Ok(count) => format!("{count} positions"),
+            Err(error) => error.to_string(),
+        });
+    }
+
+    #[test]
+    fn piped_system_receives_the_first_system_output() {
+        let mut world = World::new();
+        world.create_entity((Position,));
+        world.create_entity((Position,));
+        world
+            .add_resource(Vec::<String>::new())
+            .add_system(SystemSchedule::Update, count_positions.pipe(record));
+
+        world.run_update();
+        world.run_update();
+        assert_eq!(
+            *world.get_resource::<Vec<String>>().unwrap(),
+            ["2 positions", "2 positions"]
+        );
+    }
+
+    #[test]
+    fn piped_system_has_both_systems_access() {
+        fn write_positions(_: Query<(&mut Position,)>) {}
+
+        let piped = count_positions.pipe(record).access();
+        assert!(piped.input().is_none());
+        assert!(piped.conflicts_with(&System::access(&write_positions)));
+        assert!(piped.conflicts_with(&System::access(&record)));
+    }
+
+    #[test]
+    fn piped_systems_keep_their_own_locals_and_commands() {
+        fn first(mut runs: Local<u32>, mut commands: Commands) -> u32 {
+            *runs += 1;
+            commands.add_resource(*runs);
+            *runs * 10
+        }
+
+        fn second(In(out): In<u32>, mut runs: Local<u32>, mut log: Resource<Vec<String>>) {
+            *runs += 2;
+            log.push(format!("{out} {}", *runs));
+        }
+
+        fn third(In(()): In<()>, mut runs: Local<u32>, mut log: Resource<Vec<String>>) {
+            *runs += 3;
+            log.push(format!("third {}", *runs));
+        }
+
+        let mut world = World::new();
+        world
+            .add_resource(Vec::<String>::new())
+            .add_system(SystemSchedule::Update, first.pipe(second).pipe(third));
+
+        world.run_update();
+        world.run_update();
+        assert_eq!(
+            *world.get_resource::<Vec<String>>().unwrap(),
+            ["10 2", "third 3", "20 4", "third 6"]
+        );
+        assert_eq!(*world.get_resource::<u32>().unwrap(), 2);
+    }
+
+    #[test]
+    fn unpiped_input_is_rejected_at_registration() {
+        let error = SystemManager::new()
+            .try_add_system(SystemSchedule::Update, record)
+            .unwrap_err();
+        let SystemError::UnpipedInput { system, input } = error else {
+            panic!("expected an unpiped input, got {error}");
+        };
+        assert!(system.ends_with("record"));
+        assert!(input.contains("QueryError"));
+    }
+
+    #[test]
+    fn systems_are_rejected_from_the_state_transition_schedule() {
+        fn inspect(_: &EntityManager) {}
+
+        let mut manager = SystemManager::new();
+        let error = manager
+            .try_add_system(SystemSchedule::StateTransition, inspect)
+            .unwrap_err();
+        let SystemError::StateTransitionSchedule(system) = error else {
+            panic!("expected a rejected schedule, got {error}");
+        };
+        assert!(system.ends_with("inspect"));
+        assert!(
+            manager
+                .try_add_systems(SystemSchedule::StateTransition, (inspect, inspect))
+                .is_err()
+        );
+        assert!(manager.schedule(SystemSchedule::StateTransition).is_none());
+    }
+
+    #[test]
+    fn exclusive_systems_get_the_whole_world() {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        struct Spawn;
+
+        fn spawn(mut log: Resource<Vec<String>>) {
+            log.push("spawn".to_owned());
+        }
+
+        fn update(mut log: Resource<Vec<String>>) {
+            log.push("update".to_owned());
+        }
+
+        fn setup(world: &mut World) {
+            if world.get_resource::<Vec<String>>().is_some() {
+                return;
+            }
+            world
+                .add_resource(Vec::<String>::new())
+                .add_system(Spawn, spawn)
+                .add_system(SystemSchedule::Update, update)
+                .run_schedule(Spawn);
+        }
+
+        let mut world = World::new();
+        world.add_system(SystemSchedule::Update, setup);
+
+        world.run_update();
+        world.run_update();
+        assert_eq!(
+            *world.get_resource::<Vec<String>>().unwrap(),
+            ["spawn", "update"]
+        );
+        assert!(System::access(&setup).conflicts_with(&SystemAccess::default()));
+    }
+}
@@ -0,0 +1,315 @@
+use std::time::{Duration, Instant};
+
+/// Frame timing, shared by systems and coroutines: how long the last frame took, how long the
+/// world has run, and how many frames it has run for, scaled by [`Time::time_scale`] and
+/// frozen while [`Time::pause`]d.
+///
+/// Inserted by [`World::new`] as a resource and advanced by [`World::tick`] (with a delta of
+/// your choosing, e.g. for deterministic tests) or [`World::tick_real_time`] (with the real
+/// time since the previous tick).
+///
+/// [`World::new`]: super::world::World::new
+/// [`World::tick`]: super::world::World::tick
+/// [`World::tick_real_time`]: super::world::World::tick_real_time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Time {
+    /// Scaled duration of the last frame.
+    delta: Duration,
+    /// Sum of every scaled frame duration so far.
+    elapsed: Duration,
+    frame_count: u64,
+    time_scale: f32,
+    paused: bool,
+    /// When [`Time::real_delta`] last measured the clock.
+    last_instant: Option<Instant>,
+}
+
+impl Time {
+    /// Returns the scaled duration of the last frame: zero while paused.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Returns the scaled duration of the last frame in seconds.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Returns the scaled time elapsed over every frame so far.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the scaled time elapsed over every frame so far, in seconds.
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Returns how many frames have been advanced, paused ones included.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Returns the factor frame durations are multiplied by; `1.0` by default.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Sets the factor frame durations are multiplied by, e.g. `0.5` for slow motion.
+    /// Negative factors are treated as zero.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    /// Returns whether time is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Freezes time: frames still count, but have a zero delta.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Unfreezes time paused via [`Time::pause`].
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Advances by one frame that took `raw_delta` of unscaled time.
+    pub fn advance(&mut self, raw_delta: Duration) {
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else {
+            raw_delta.mul_f32(self.time_scale)
+        };
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+    }
+
+    /// Returns the real time since this was last called, zero the first time.
+    pub(crate) fn real_delta(&mut self) -> Duration {
+        let now = Instant::now();
+        let delta = self
+            .last_instant
+            .map_or(Duration::ZERO, |last| now.duration_since(last));
+        self.last_instant = Some(now);
+        delta
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+            time_scale: 1.0,
+            paused: false,
+            last_instant: None,
+        }
+    }
+}
+
+/// Drives [`SystemSchedule::FixedUpdate`](super::system::SystemSchedule::FixedUpdate): scaled
+/// frame time from [`Time`] is fed into an accumulator, and each [`World::run_update`] runs
+/// the schedule once per whole step accumulated, so its systems always see the same step
+/// however fast frames render.
+///
+/// Inserted by [`World::new`] as a resource, stepping at 60 Hz. Replace it via
+/// [`World::add_resource`] to change the step, and read [`FixedTime::alpha`] in render systems
+/// to interpolate between the last two fixed steps.
+///
+/// [`World::run_update`]: super::world::World::run_update
+/// [`World::new`]: super::world::World::new
+/// [`World::add_resource`]: super::world::World::add_resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedTime {
+    step: Duration,
+    /// Most steps run by a single [`World::run_update`](super::world::World::run_update);
+    /// time beyond that is dropped rather than caught up on later.
+    max_steps: u32,
+    /// Time fed in but not yet consumed by a step.
+    accumulator: Duration,
+    /// Steps run by the last [`World::run_update`](super::world::World::run_update).
+    steps_run: u32,
+}
+
+impl FixedTime {
+    /// Default for [`FixedTime::max_steps`].
+    pub const DEFAULT_MAX_STEPS: u32 = 5;
+
+    /// Creates an accumulator running one fixed step per `step` of frame time.
+    ///
+    /// # Panics
+    /// Panics if `step` is zero.
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "FixedTime step must be non-zero");
+        Self {
+            step,
+            max_steps: Self::DEFAULT_MAX_STEPS,
+            accumulator: Duration::ZERO,
+            steps_run: 0,
+        }
+    }
+
+    /// Creates an accumulator running `hz` fixed steps per second of frame time.
+    ///
+    /// # Panics
+    /// Panics if `hz` isn't a positive, finite rate.
+    pub fn from_hz(hz: f64) -> Self {
+        assert!(
+            hz > 0.0 && hz.is_finite(),
+            "FixedTime rate must be positive and finite, got {hz} Hz"
+        );
+        Self::new(Duration::from_secs_f64(1.0 / hz))
+    }
+
+    /// Returns the fixed step, the time every fixed-update system should advance by.
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Returns the fixed step in seconds.
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Returns the most steps a single update runs to catch up.
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// Sets the most steps a single update runs to catch up, e.g. after a long frame. Time
+    /// beyond that is dropped, so the simulation slows down rather than spiralling.
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    /// Feeds `delta_time` seconds of frame time into the accumulator.
+    pub fn accumulate(&mut self, delta_time: f32) {
+        self.accumulator += Duration::from_secs_f32(delta_time.max(0.0));
+    }
+
+    /// Returns the time fed in but not yet consumed by a step.
+    pub fn accumulated(&self) -> Duration {
+        self.accumulator
+    }
+
+    /// Returns how far, in `[0, 1)`, the accumulator is into the next step: the interpolation
+    /// factor between the state after the last fixed step and the one after the next.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// Returns how many steps the last update ran.
+    pub fn steps_run(&self) -> u32 {
+        self.steps_run
+    }
+
+    /// Consumes every whole step accumulated, returning how many should run: at most
+    /// [`FixedTime::max_steps`], the rest being dropped.
+    pub(crate) fn expend(&mut self) -> u32 {
+        let step = self.step.as_nanos();
+        let steps = self.accumulator.as_nanos() / step;
+        self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % step) as u64);
+        self.steps_run = steps.min(self.max_steps as u128) as u32;
+        self.steps_run
+    }
+}
+
+impl Default for FixedTime {
+    /// Steps at 60 Hz.
+    fn default() -> Self {
+        Self::from_hz(60.0)
+    }
+}
+
+#[cfg(test)]
+mod time_test {
+    use std::time::Duration;
+
+    use super::{FixedTime, Time};
+    use crate::core::{
+        coroutine::{Coroutine, CoroutineState, WaitAmountOfSeconds},
+        resources::Resource,
+        system::SystemSchedule,
+        world::World,
+    };
+
+    #[test]
+    fn whole_steps_are_expended_and_the_remainder_drives_alpha() {
+        let mut time = FixedTime::new(Duration::from_millis(100));
+        time.accumulate(0.25);
+        assert_eq!(time.expend(), 2);
+        assert!((time.alpha() - 0.5).abs() < 1e-4);
+
+        // A long frame only catches up `max_steps`, dropping the rest.
+        time.set_max_steps(3);
+        time.accumulate(1.0);
+        assert_eq!(time.expend(), 3);
+        assert!((time.alpha() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    #[should_panic(expected = "FixedTime rate must be positive and finite, got 0 Hz")]
+    fn zero_rate_is_rejected() {
+        FixedTime::from_hz(0.0);
+    }
+
+    #[test]
+    fn fixed_update_runs_once_per_accumulated_step() {
+        fn physics(mut steps: Resource<u32>) {
+            *steps += 1;
+        }
+
+        let mut world = World::new();
+        world
+            .add_resource(FixedTime::from_hz(10.0))
+            .add_resource(0u32)
+            .add_system(SystemSchedule::FixedUpdate, physics);
+
+        let mut runs = Vec::new();
+        for _ in 0..4 {
+            world.tick(0.15);
+            runs.push(world.get_resource::<FixedTime>().unwrap().steps_run());
+        }
+        assert_eq!(runs, [1, 2, 1, 2]);
+        assert_eq!(*world.get_resource::<u32>().unwrap(), 6);
+    }
+
+    #[test]
+    fn scaled_and_paused_time_is_shared_by_systems_and_coroutines() {
+        struct Resumes(Vec<f32>);
+
+        fn record_elapsed(time: Resource<Time>, mut seen: Resource<Vec<f32>>) {
+            seen.push(time.elapsed_seconds());
+        }
+
+        let mut world = World::new();
+        world
+            .add_resource(Vec::<f32>::new())
+            .add_resource(Resumes(Vec::new()))
+            .add_system(SystemSchedule::Update, record_elapsed)
+            .add_coroutine(Coroutine::new("wait", |world| {
+                let elapsed = world.get_resource::<Time>().unwrap().elapsed_seconds();
+                world.get_resource::<Resumes>().unwrap().0.push(elapsed);
+                CoroutineState::Yielded(WaitAmountOfSeconds {
+                    amount_in_seconds: 0.5,
+                })
+            }));
+
+        world.tick(1.0);
+        world.get_resource::<Time>().unwrap().set_time_scale(0.5);
+        world.tick(1.0);
+        world.get_resource::<Time>().unwrap().pause();
+        world.tick(1.0);
+
+        let time = world.get_resource::<Time>().unwrap();
+        assert_eq!(time.frame_count(), 3);
+        assert_eq!(time.delta_seconds(), 0.0);
+        assert_eq!(*world.get_resource::<Vec<f32>>().unwrap(), [1.0, 1.5, 1.5]);
+        // The coroutine's wait elapses in scaled time, and not at all while paused.
+        assert_eq!(world.get_resource::<Resumes>().unwrap().0, [1.0, 1.5]);
+    }
+}
@@ -1,16 +1,22 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
 
 use super::{
+    cell::AtomicRefCell,
+    change_detection::CHECK_TICK_THRESHOLD,
     component::{BundleComponent, Component},
     coordinator::Coordinator,
     coroutine::{Coroutine, CoroutineManager},
     entity::Entity,
     entity_manager::EntityManager,
+    error::{SystemError, SystemFailure},
     event::{EventHandler, EventManager},
     extension::Extension,
-    query::{Query, QueryConstraint, QueryParams},
+    query::{Query, QueryConstraint, QueryParams, ReadOnlyQueryParams},
     resources::{Resource, ResourceManager},
-    system::{IntoSystem, SystemBundle, SystemManager, SystemSchedule},
+    schedule::ScheduleLabel,
+    state::{NextState, State, States},
+    system::{IntoSystemConfig, SystemBundle, SystemManager, SystemSchedule},
+    time::{FixedTime, Time},
 };
 
 /// The central entry point of the ECS: owns the entity, system, event, resource and
@@ -28,42 +34,46 @@ use super::{
 /// ```
 pub struct World {
     /// Owns all entities/components/archetypes. See [`EntityManager`].
-    pub entity_manager: Rc<RefCell<EntityManager>>,
-    /// Owns registered systems, grouped by [`SystemSchedule`].
-    pub system_manager: Rc<RefCell<SystemManager>>,
+    pub entity_manager: Arc<AtomicRefCell<EntityManager>>,
+    /// Owns registered systems, grouped by [`ScheduleLabel`].
+    pub system_manager: Arc<AtomicRefCell<SystemManager>>,
     /// Owns event subscriptions and dispatches published events. See [`EventManager`].
-    pub event_manager: Rc<RefCell<EventManager>>,
+    pub event_manager: Arc<AtomicRefCell<EventManager>>,
     /// Owns global resources. See [`ResourceManager`].
-    pub resources: Rc<RefCell<ResourceManager>>,
+    pub resources: Arc<AtomicRefCell<ResourceManager>>,
     /// Owns running coroutines. See [`CoroutineManager`].
-    pub coroutine_manager: Rc<RefCell<CoroutineManager>>,
+    pub coroutine_manager: Arc<AtomicRefCell<CoroutineManager>>,
     /// Extensions registered via [`World::add_extension`], applied by [`World::build`].
     pub extensions: Rc<RefCell<Vec<Box<dyn Extension>>>>,
     /// Lightweight handle shared with systems, giving them access to every manager above
     /// without holding a reference to the `World` itself. `None` only before [`World::new`]
     /// finishes constructing it.
-    pub coordinator: Option<Rc<RefCell<Coordinator>>>,
+    pub coordinator: Option<Arc<Coordinator>>,
 }
 
 impl World {
-    /// Creates a new, empty `World` with all managers initialized and wired together.
+    /// Creates a new, empty `World` with all managers initialized and wired together, and
+    /// default [`Time`] and [`FixedTime`] resources.
     pub fn new() -> Self {
         let mut world = Self {
-            entity_manager: Rc::new(RefCell::new(EntityManager::new())),
-            system_manager: Rc::new(RefCell::new(SystemManager::new())),
-            event_manager: Rc::new(RefCell::new(EventManager::default())),
-            resources: Rc::new(RefCell::new(ResourceManager::new())),
-            coroutine_manager: Rc::new(RefCell::new(CoroutineManager::new())),
+            entity_manager: Arc::new(AtomicRefCell::new(EntityManager::new())),
+            system_manager: Arc::new(AtomicRefCell::new(SystemManager::new())),
+            event_manager: Arc::new(AtomicRefCell::new(EventManager::default())),
+            resources: Arc::new(AtomicRefCell::new(ResourceManager::new())),
+            coroutine_manager: Arc::new(AtomicRefCell::new(CoroutineManager::new())),
             extensions: Rc::new(RefCell::new(Vec::new())),
             coordinator: None,
         };
 
-        let coordinator = Rc::new(RefCell::new(Coordinator::new(&world)));
+        let coordinator = Arc::new(Coordinator::new(&world));
         world
             .event_manager
             .borrow_mut()
-            .bind_coordinator(Rc::downgrade(&coordinator));
+            .bind_coordinator(Arc::downgrade(&coordinator));
         world.coordinator = Some(coordinator);
+        world
+            .add_resource(Time::default())
+            .add_resource(FixedTime::default());
 
         world
     }
@@ -79,54 +89,84 @@ impl World {
         self.entity_manager.borrow_mut().create_entity(components)
     }
 
-    /// Removes component `T` from `entity`, if present. Returns `&mut Self` for chaining.
+    /// Removes component `T` from `entity`, if present. No-op if `entity` no longer exists.
+    /// Returns `&mut Self` for chaining.
     pub fn remove_component<T: 'static + Component>(&mut self, entity: Entity) -> &mut Self {
-        self.entity_manager
+        let _ = self
+            .entity_manager
             .borrow_mut()
             .remove_component::<T>(entity);
         self
     }
 
-    /// Adds (or replaces) component `T` on an existing `entity`. Returns `&mut Self` for chaining.
+    /// Adds (or replaces) component `T` on an existing `entity`. No-op if `entity` no longer
+    /// exists. Returns `&mut Self` for chaining.
     pub fn add_component_to_entity<T: 'static + Component>(
         &mut self,
         entity: Entity,
         component: T,
     ) -> &mut Self {
-        self.entity_manager
+        let _ = self
+            .entity_manager
             .borrow_mut()
             .add_component_to_entity(entity, component);
         self
     }
 
-    /// Removes `entity` and all of its components from the world. Returns `&mut Self` for chaining.
+    /// Removes `entity` and all of its components from the world. No-op if `entity` no longer
+    /// exists. Returns `&mut Self` for chaining.
     pub fn remove_entity(&mut self, entity: Entity) -> &mut Self {
-        self.entity_manager.borrow_mut().remove_entity(entity);
+        let _ = self.entity_manager.borrow_mut().remove_entity(entity);
         self
     }
 
-    /// Builds a [`Query`] over entities that have every component type in `T`.
-    pub fn create_query<'a, T: QueryParams<'a>>(&'a self) -> Query<'a, T> {
-        let entity_manager = self.entity_manager.clone();
-        let archetype_ptr = unsafe { &(*entity_manager.as_ptr()).archetypes };
-        Query::<T>::new(archetype_ptr)
+    /// Builds a read-only [`Query`] over entities that have every component type in `T`.
+    pub fn create_query<T: ReadOnlyQueryParams>(&self) -> Query<'_, T> {
+        let entity_manager = unsafe { &*self.entity_manager.as_ptr() };
+        Query::<T>::new(entity_manager)
+    }
+
+    /// Like [`World::create_query`], for queries that fetch components mutably. Borrows the
+    /// world mutably, so no other query can hand out the same components meanwhile.
+    pub fn create_query_mut<T: QueryParams>(&mut self) -> Query<'_, T> {
+        let entity_manager = unsafe { &mut *self.entity_manager.as_ptr() };
+        Query::<T>::new_mut(entity_manager)
     }
 
     /// Like [`World::create_query`], additionally filtering entities by constraint `C`
     /// (e.g. [`Without`](super::query::Without)).
-    pub fn create_query_with_constraint<'a, T: QueryParams<'a>, C: QueryConstraint>(
-        &'a self,
-    ) -> Query<'a, T, C> {
-        let entity_manager = self.entity_manager.clone();
-        let archetype_ptr = unsafe { &(*entity_manager.as_ptr()).archetypes };
-        Query::<T, C>::new(archetype_ptr)
+    pub fn create_query_with_constraint<T: ReadOnlyQueryParams, C: QueryConstraint>(
+        &self,
+    ) -> Query<'_, T, C> {
+        let entity_manager = unsafe { &*self.entity_manager.as_ptr() };
+        Query::<T, C>::new(entity_manager)
+    }
+
+    /// Like [`World::create_query_with_constraint`], for queries that fetch components mutably.
+    pub fn create_query_with_constraint_mut<T: QueryParams, C: QueryConstraint>(
+        &mut self,
+    ) -> Query<'_, T, C> {
+        let entity_manager = unsafe { &mut *self.entity_manager.as_ptr() };
+        Query::<T, C>::new_mut(entity_manager)
     }
 
-    /// Registers `system` to run during `system_scheduler`. Returns `&mut Self` for chaining.
+    /// Registers `system` to run during the schedule labeled `system_scheduler`: one of the
+    /// built-in [`SystemSchedule`]s or a [`ScheduleLabel`] of your own. It can be labeled and
+    /// ordered relative to other systems through [`IntoSystemConfig`]. Returns `&mut Self` for
+    /// chaining.
+    ///
+    /// # Panics
+    /// Panics if two of the system's own parameters conflict (e.g. `&mut EntityManager` next to
+    /// a `Query`), its ordering constraints form a cycle, or `system_scheduler` is
+    /// [`SystemSchedule::StateTransition`]; use [`World::try_add_system`] to handle that as an
+    /// error instead.
+    ///
+    /// [`SystemSchedule`]: super::system::SystemSchedule
+    /// [`SystemSchedule::StateTransition`]: super::system::SystemSchedule::StateTransition
     pub fn add_system<P>(
         &mut self,
-        system_scheduler: SystemSchedule,
-        system: impl IntoSystem<P>,
+        system_scheduler: impl ScheduleLabel,
+        system: impl IntoSystemConfig<P>,
     ) -> &mut Self {
         self.system_manager
             .borrow_mut()
@@ -134,31 +174,259 @@ impl World {
         self
     }
 
-    /// Registers a tuple of systems to run during `action`. Returns `&mut Self` for chaining.
-    pub fn add_systems<P: 'static>(
+    /// Like [`World::add_system`], but returns a [`SystemError`] instead of panicking if the
+    /// system is rejected, leaving the world unchanged.
+    pub fn try_add_system<P>(
         &mut self,
-        action: SystemSchedule,
+        system_scheduler: impl ScheduleLabel,
+        system: impl IntoSystemConfig<P>,
+    ) -> Result<&mut Self, SystemError> {
+        self.system_manager
+            .borrow_mut()
+            .try_add_system(system_scheduler, system)?;
+        Ok(self)
+    }
+
+    /// Registers a tuple of systems to run during `action`; see [`SystemBundle`] for running
+    /// them in sequence via `.chain()`. Returns `&mut Self` for chaining.
+    ///
+    /// # Panics
+    /// Panics if any of the systems is rejected, like [`World::add_system`].
+    pub fn add_systems<P>(
+        &mut self,
+        action: impl ScheduleLabel,
         systems: impl SystemBundle<P>,
     ) -> &mut Self {
-        systems.add_systems(action, &mut self.system_manager.borrow_mut());
+        self.system_manager
+            .borrow_mut()
+            .add_systems(action, systems);
         self
     }
 
+    /// Like [`World::add_systems`], but returns a [`SystemError`] instead of panicking if any
+    /// of the systems is rejected, leaving the world unchanged.
+    pub fn try_add_systems<P>(
+        &mut self,
+        action: impl ScheduleLabel,
+        systems: impl SystemBundle<P>,
+    ) -> Result<&mut Self, SystemError> {
+        self.system_manager
+            .borrow_mut()
+            .try_add_systems(action, systems)?;
+        Ok(self)
+    }
+
     /// Runs every system registered under [`SystemSchedule::Startup`]. Call once, before the
     /// first [`World::run_update`].
+    ///
+    /// [`SystemSchedule::Startup`]: super::system::SystemSchedule::Startup
     pub fn run_startup(&mut self) -> &mut Self {
-        self.system_manager.borrow_mut().run_startup_systems(self);
+        self.run_labeled(&SystemSchedule::Startup);
         self
     }
 
-    /// Runs every system registered under [`SystemSchedule::Update`]. Call once per frame/tick.
+    /// Runs every update stage in order — by default [`SystemSchedule::PreUpdate`],
+    /// [`SystemSchedule::StateTransition`] (applying state transitions, see
+    /// [`World::init_state`]), [`SystemSchedule::FixedUpdate`] (once per step accumulated via
+    /// [`World::advance_fixed_time`]), [`SystemSchedule::Update`] and
+    /// [`SystemSchedule::PostUpdate`]; see [`World::add_stage_after`] to add your own. Call
+    /// once per frame/tick.
+    ///
+    /// Each system run advances the change tick that [`Added`](super::query::Added),
+    /// [`Changed`](super::query::Changed) and
+    /// [`RemovedComponents`](super::change_detection::RemovedComponents) compare against, and
+    /// removals older than the previous frame are forgotten here, as are stored ticks clamped
+    /// (see [`World::check_change_ticks`]).
+    ///
+    /// [`SystemSchedule::PreUpdate`]: super::system::SystemSchedule::PreUpdate
+    /// [`SystemSchedule::StateTransition`]: super::system::SystemSchedule::StateTransition
+    /// [`SystemSchedule::FixedUpdate`]: super::system::SystemSchedule::FixedUpdate
+    /// [`SystemSchedule::Update`]: super::system::SystemSchedule::Update
+    /// [`SystemSchedule::PostUpdate`]: super::system::SystemSchedule::PostUpdate
     pub fn run_update(&self) {
-        self.system_manager.borrow_mut().run_update_systems(self);
+        self.check_change_ticks();
+        self.entity_manager.borrow_mut().begin_frame();
+        let stages = self.system_manager.borrow().update_stages().to_vec();
+        let state_transition: &dyn ScheduleLabel = &SystemSchedule::StateTransition;
+        let fixed_update: &dyn ScheduleLabel = &SystemSchedule::FixedUpdate;
+        for stage in stages {
+            if *stage == *state_transition {
+                self.apply_state_transitions();
+            } else if *stage == *fixed_update {
+                self.run_fixed_update();
+            } else {
+                self.run_labeled(&*stage);
+            }
+        }
+    }
+
+    /// Clamps every stored change tick — of components, removals, systems and run conditions —
+    /// to at most [`MAX_CHANGE_AGE`](super::change_detection::MAX_CHANGE_AGE) ticks before the
+    /// current one, if the change tick advanced far enough since the last time. Without it,
+    /// ticks left untouched for long would look new again once the `u32` change tick wraps
+    /// around. Called by [`World::run_update`]; only needed when running schedules another way
+    /// for billions of system runs.
+    pub fn check_change_ticks(&self) {
+        let mut entity_manager = self.entity_manager.borrow_mut();
+        let change_tick = entity_manager.change_tick;
+        if change_tick.wrapping_sub(entity_manager.last_check_tick) < CHECK_TICK_THRESHOLD {
+            return;
+        }
+        entity_manager.clamp_change_ticks();
+        self.system_manager
+            .borrow_mut()
+            .clamp_change_ticks(change_tick);
     }
 
     /// Runs every system registered under [`SystemSchedule::Shutdown`].
+    ///
+    /// [`SystemSchedule::Shutdown`]: super::system::SystemSchedule::Shutdown
     pub fn run_shutdown(&self) {
-        self.system_manager.borrow_mut().run_shutdown_systems(self);
+        self.run_labeled(&SystemSchedule::Shutdown);
+    }
+
+    /// Runs one frame that took `delta_time` seconds: advances the [`Time`] resource, feeds its
+    /// scaled delta to [`FixedTime`], runs [`World::run_update`], then advances coroutines by
+    /// the same scaled delta. Call once per frame instead of those individually; passing a
+    /// fixed delta keeps tests deterministic.
+    pub fn tick(&mut self, delta_time: f32) -> &mut Self {
+        let delta_time = match self.get_resource::<Time>() {
+            Some(mut time) => {
+                time.advance(Duration::from_secs_f32(delta_time.max(0.0)));
+                time.delta_seconds()
+            }
+            None => delta_time,
+        };
+        self.advance_fixed_time(delta_time);
+        self.run_update();
+        self.update_coroutines(delta_time);
+        self
+    }
+
+    /// Like [`World::tick`], with the real time elapsed since the previous call (zero the
+    /// first time).
+    pub fn tick_real_time(&mut self) -> &mut Self {
+        let delta_time = self
+            .get_resource::<Time>()
+            .map_or(0.0, |mut time| time.real_delta().as_secs_f32());
+        self.tick(delta_time)
+    }
+
+    /// Feeds `delta_time` seconds of frame time into the [`FixedTime`] resource, so the next
+    /// [`World::run_update`] runs [`SystemSchedule::FixedUpdate`] once per whole fixed step
+    /// accumulated. Call once per frame, before `run_update`, unless using [`World::tick`],
+    /// which does so itself. No-op if the resource was removed.
+    ///
+    /// [`SystemSchedule::FixedUpdate`]: super::system::SystemSchedule::FixedUpdate
+    pub fn advance_fixed_time(&mut self, delta_time: f32) -> &mut Self {
+        if let Some(mut time) = self.get_resource::<FixedTime>() {
+            time.accumulate(delta_time);
+        }
+        self
+    }
+
+    /// Sets the function called with every error a system returns — e.g.
+    /// [`log_error`](super::error_handler::log_error) or
+    /// [`collect_errors`](super::error_handler::collect_errors) — replacing the default,
+    /// [`panic_on_error`](super::error_handler::panic_on_error). Returns `&mut Self` for
+    /// chaining.
+    pub fn set_error_handler(
+        &mut self,
+        error_handler: impl Fn(&World, SystemFailure) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.system_manager
+            .borrow_mut()
+            .set_error_handler(error_handler);
+        self
+    }
+
+    /// Adds state `S`, starting at `initial`: inserts the [`State<S>`] and [`NextState<S>`]
+    /// resources, and has [`World::run_update`] apply transitions queued through
+    /// [`NextState::set`], running the [`OnExit`](super::state::OnExit),
+    /// [`OnTransition`](super::state::OnTransition) and [`OnEnter`](super::state::OnEnter)
+    /// schedules. `OnEnter(initial)` runs at the first update. Returns `&mut Self` for
+    /// chaining.
+    ///
+    /// Calling this again for the same `S` starts it over from `initial`.
+    pub fn init_state<S: States>(&mut self, initial: S) -> &mut Self {
+        self.add_resource(State(initial))
+            .add_resource(NextState::<S>(None));
+        self.system_manager.borrow_mut().add_state::<S>();
+        self
+    }
+
+    /// Runs every system registered under the schedule labeled `schedule`, whether or not it's
+    /// an update stage. No-op if there is none.
+    pub fn run_schedule(&self, schedule: impl ScheduleLabel) {
+        self.run_labeled(&schedule);
+    }
+
+    /// Runs the schedule labeled `label`, without keeping the [`SystemManager`] borrowed while
+    /// its systems run.
+    fn run_labeled(&self, label: &dyn ScheduleLabel) {
+        let coordinator = self
+            .coordinator
+            .clone()
+            .expect("Coordinator not initialized - call World::new() before running systems");
+        let taken = self.system_manager.borrow_mut().take_schedule(label);
+        if let Some((label, mut schedule, error_handler)) = taken {
+            schedule.run(self, &coordinator, &error_handler);
+            self.system_manager
+                .borrow_mut()
+                .restore_schedule(label, schedule);
+        }
+    }
+
+    /// Applies the queued transition of every state added via [`World::init_state`].
+    fn apply_state_transitions(&self) {
+        let mut transitions = self.system_manager.borrow_mut().take_state_transitions();
+        for (_, transition) in transitions.iter_mut() {
+            transition(self);
+        }
+        self.system_manager
+            .borrow_mut()
+            .restore_state_transitions(transitions);
+    }
+
+    /// Runs [`SystemSchedule::FixedUpdate`] once per whole step accumulated in the
+    /// [`FixedTime`] resource, if any.
+    fn run_fixed_update(&self) {
+        let Some(steps) = self.get_resource::<FixedTime>().map(|mut time| time.expend()) else {
+            return;
+        };
+        for _ in 0..steps {
+            self.run_labeled(&SystemSchedule::FixedUpdate);
+        }
+    }
+
+    /// Makes `stage` an update stage run by [`World::run_update`] right before `before`.
+    /// Returns `&mut Self` for chaining.
+    ///
+    /// # Panics
+    /// Panics if `before` isn't an update stage, or `stage` already is.
+    pub fn add_stage_before(
+        &mut self,
+        before: impl ScheduleLabel,
+        stage: impl ScheduleLabel,
+    ) -> &mut Self {
+        self.system_manager
+            .borrow_mut()
+            .add_stage_before(before, stage);
+        self
+    }
+
+    /// Makes `stage` an update stage run by [`World::run_update`] right after `after`.
+    /// Returns `&mut Self` for chaining.
+    ///
+    /// # Panics
+    /// Panics if `after` isn't an update stage, or `stage` already is.
+    pub fn add_stage_after(
+        &mut self,
+        after: impl ScheduleLabel,
+        stage: impl ScheduleLabel,
+    ) -> &mut Self {
+        self.system_manager.borrow_mut().add_stage_after(after, stage);
+        self
     }
 
     /// Publishes `event` immediately, synchronously invoking whichever handler is subscribed
@@ -171,7 +439,7 @@ impl World {
 
     /// Subscribes `system` as the handler for events of type `T`, replacing any previous
     /// handler for that type. Returns `&mut Self` for chaining.
-    pub fn subscribe_event<T: 'static, FUNC: 'static + Fn(&World, T)>(
+    pub fn subscribe_event<T: 'static, FUNC: 'static + Fn(&World, T) + Send + Sync>(
         &mut self,
         system: FUNC,
     ) -> &mut Self {
@@ -182,14 +450,58 @@ impl World {
 
     /// Inserts `resource`, replacing any existing resource of the same type. Returns `&mut Self`
     /// for chaining.
-    pub fn add_resource<T: 'static>(&mut self, resource: T) -> &mut Self {
+    pub fn add_resource<T: 'static + Send + Sync>(&mut self, resource: T) -> &mut Self {
         self.resources.borrow_mut().add(resource);
         self
     }
 
     /// Returns a handle to the resource of type `T`, or `None` if it hasn't been added.
     pub fn get_resource<T: 'static>(&self) -> Option<Resource<T>> {
-        self.resources.borrow().get_resource::<T>()
+        self.resources.borrow().fetch::<T>()
+    }
+
+    /// Returns whether a resource of type `T` has been added.
+    pub fn contains_resource<T: 'static>(&self) -> bool {
+        self.resources.borrow().contains_resource::<T>()
+    }
+
+    /// Adds the resource of type `T` built via [`FromWorld`] (e.g. `T::default()`), unless
+    /// one was already added. Returns `&mut Self` for chaining.
+    pub fn init_resource<T: FromWorld + Send + Sync + 'static>(&mut self) -> &mut Self {
+        if !self.contains_resource::<T>() {
+            let resource = T::from_world(self);
+            self.add_resource(resource);
+        }
+        self
+    }
+
+    /// Removes the resource of type `T` and returns it, or `None` if it hasn't been added.
+    ///
+    /// # Panics
+    /// Panics if a handle to it returned by [`World::get_resource`] is still alive.
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources.borrow_mut().remove_resource::<T>()
+    }
+
+    /// Takes the resource of type `T` out of the world while `f` runs with both, so `f` can
+    /// use the resource and the rest of the world at once, then puts it back (replacing any
+    /// resource of the same type added meanwhile), even if `f` panics. Returns what `f` returns.
+    ///
+    /// # Panics
+    /// Panics if the resource hasn't been added, or if a handle to it is still alive.
+    pub fn resource_scope<T: Send + Sync + 'static, R>(
+        &mut self,
+        f: impl FnOnce(&mut World, &mut T) -> R,
+    ) -> R {
+        let resource = self.remove_resource::<T>().unwrap_or_else(|| {
+            panic!("resource {} hasn't been added", std::any::type_name::<T>())
+        });
+        let mut scope = ResourceScope {
+            world: self,
+            resource: Some(resource),
+        };
+        let ResourceScope { world, resource } = &mut scope;
+        f(world, resource.as_mut().unwrap())
     }
 
     /// Starts running `coroutine`. Returns `&mut Self` for chaining.
@@ -208,7 +520,8 @@ impl World {
         self.coroutine_manager.borrow_mut().stop_by_name(name);
     }
 
-    /// Advances all running coroutines by `delta_time` seconds. Call once per frame.
+    /// Advances all running coroutines by `delta_time` seconds. Call once per frame, unless
+    /// using [`World::tick`], which does so with the [`Time`] resource's scaled delta.
     pub fn update_coroutines(&mut self, delta_time: f32) {
         let coroutine_manager = self.coroutine_manager.clone();
         coroutine_manager.borrow_mut().update(self, delta_time);
@@ -232,30 +545,46 @@ impl World {
         self
     }
 
-    pub(crate) fn from_coordinator(coordinator: Rc<RefCell<Coordinator>>) -> Self {
-        let (entity_manager, system_manager, event_manager, resources, coroutine_manager) = {
-            let c = coordinator.borrow();
-            (
-                c.entity_manager.clone(),
-                c.system_manager.clone(),
-                c.event_manager.clone(),
-                c.resources.clone(),
-                c.coroutine_manager.clone(),
-            )
-        };
-
+    pub(crate) fn from_coordinator(coordinator: Arc<Coordinator>) -> Self {
         World {
-            entity_manager,
-            system_manager,
-            event_manager,
-            resources,
-            coroutine_manager,
+            entity_manager: coordinator.entity_manager.clone(),
+            system_manager: coordinator.system_manager.clone(),
+            event_manager: coordinator.event_manager.clone(),
+            resources: coordinator.resources.clone(),
+            coroutine_manager: coordinator.coroutine_manager.clone(),
             extensions: Rc::new(RefCell::new(Vec::new())),
             coordinator: Some(coordinator),
         }
     }
 }
 
+/// Puts the resource taken out by [`World::resource_scope`] back when dropped, including while
+/// unwinding from a panicking scope.
+struct ResourceScope<'w, T: Send + Sync + 'static> {
+    world: &'w mut World,
+    resource: Option<T>,
+}
+
+impl<T: Send + Sync + 'static> Drop for ResourceScope<'_, T> {
+    fn drop(&mut self) {
+        if let Some(resource) = self.resource.take() {
+            self.world.add_resource(resource);
+        }
+    }
+}
+
+/// Builds a value from the [`World`], e.g. a resource derived from other resources; see
+/// [`World::init_resource`]. Implemented for every `Default` type.
+pub trait FromWorld {
+    fn from_world(world: &mut World) -> Self;
+}
+
+impl<T: Default> FromWorld for T {
+    fn from_world(_world: &mut World) -> Self {
+        T::default()
+    }
+}
+
 impl Default for World {
     fn default() -> Self {
         Self::new()
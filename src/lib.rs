@@ -1,4 +1,4 @@
-//! A simple, single-threaded Entity Component System.
+//! A simple Entity Component System whose non-conflicting systems run in parallel.
 //!
 //! ```
 //! use dark_iron_ecs::core::world::World;